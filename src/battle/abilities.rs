@@ -0,0 +1,314 @@
+use std::error::Error;
+
+use battle::magic::Spell;
+use battle::monster::Monster;
+use rom_map;
+use text;
+use utils::check_len;
+
+const NUM_BLITZES: usize = 8;
+const BLITZ_CODE_SIZE: usize = 12;
+const NUM_SWDTECHS: usize = 8;
+const SWDTECH_NAME_SIZE: usize = 12;
+const NUM_RAGES: usize = 0x100;
+const NUM_DANCES: usize = 8;
+const DANCE_NAME_SIZE: usize = 12;
+const NUM_DANCE_ATTACKS: usize = 4;
+
+// Blitz and SwdTech attacks are consecutive spells.
+const FIRST_BLITZ_SPELL: u8 = 0x5d;
+const FIRST_SWDTECH_SPELL: u8 = 0x55;
+
+// Reel combinations in the order of the slot attack table.
+pub const SLOT_COMBOS: [&str; 8] = [
+    "7-7-7",
+    "7-7-Bar",
+    "Bar-Bar-Bar",
+    "Airship x3",
+    "Chocobo x3",
+    "Dragon x3",
+    "Diamond x3",
+    "Mismatch",
+];
+
+// Names of Blitz input codes 0x01 and up.
+const BLITZ_INPUTS: [&str; 12] = [
+    "R",
+    "L",
+    "X",
+    "Y",
+    "Up",
+    "Up-Right",
+    "Right",
+    "Down-Right",
+    "Down",
+    "Down-Left",
+    "Left",
+    "Up-Left",
+];
+const NO_BLITZ_INPUT: u8 = 0xff;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Blitz {
+    pub spell: u8,
+    // Raw input codes.  Unused codes are 0xff.
+    pub codes: Vec<u8>,
+}
+
+impl Blitz {
+    // Button and direction names of the inputs.  Unknown codes are shown in
+    // hex.
+    pub fn inputs(&self) -> Vec<String> {
+        self.codes
+            .iter()
+            .filter(|c| **c != NO_BLITZ_INPUT)
+            .map(|c| match BLITZ_INPUTS.get((*c as usize).wrapping_sub(1)) {
+                Some(name) => name.to_string(),
+                None => format!("{:02x}", c),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct SwdTech {
+    pub name: String,
+    pub spell: u8,
+}
+
+// The two attacks Gau can use while raging as `monster`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Rage {
+    pub monster: usize,
+    pub attacks: [u8; 2],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Dance {
+    pub name: String,
+    pub attacks: [u8; NUM_DANCE_ATTACKS],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Slot {
+    pub combo: String,
+    pub spell: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Abilities {
+    pub blitzes: Vec<Blitz>,
+    pub swdtechs: Vec<SwdTech>,
+    pub lores: Vec<u8>,
+    pub rages: Vec<Rage>,
+    pub dances: Vec<Dance>,
+    // Dance Mog gets from fighting on each battle background.
+    pub background_dances: Vec<u8>,
+    pub slots: Vec<Slot>,
+}
+
+fn table(rom_data: &[u8], snes_addr: usize, len: usize) -> Result<&[u8], Box<Error>> {
+    let addr = rom_map::snes_to_file(snes_addr);
+    check_len(rom_data, addr, len)?;
+    Ok(&rom_data[addr..addr + len])
+}
+
+// Lores are the spells flagged as learnable by Strago.
+pub fn parse(rom_data: &[u8], spells: &[Spell]) -> Result<Abilities, Box<Error>> {
+    let blitzes = table(
+        rom_data,
+        rom_map::BLITZ_CODES,
+        NUM_BLITZES * BLITZ_CODE_SIZE,
+    )?
+    .chunks(BLITZ_CODE_SIZE)
+    .enumerate()
+    .map(|(i, c)| Blitz {
+        spell: FIRST_BLITZ_SPELL + i as u8,
+        codes: c.to_vec(),
+    })
+    .collect();
+
+    let swdtechs = table(
+        rom_data,
+        rom_map::SWDTECH_NAMES,
+        NUM_SWDTECHS * SWDTECH_NAME_SIZE,
+    )?
+    .chunks(SWDTECH_NAME_SIZE)
+    .enumerate()
+    .map(|(i, n)| SwdTech {
+        name: text::decode_name(n),
+        spell: FIRST_SWDTECH_SPELL + i as u8,
+    })
+    .collect();
+
+    let lores = spells
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.lore)
+        .map(|(i, _)| i as u8)
+        .collect();
+
+    let rages = table(rom_data, rom_map::RAGE_ATTACKS, NUM_RAGES * 2)?
+        .chunks(2)
+        .enumerate()
+        .map(|(i, a)| Rage {
+            monster: i,
+            attacks: [a[0], a[1]],
+        })
+        .collect();
+
+    let names = table(rom_data, rom_map::DANCE_NAMES, NUM_DANCES * DANCE_NAME_SIZE)?;
+    let dances = table(
+        rom_data,
+        rom_map::DANCE_ATTACKS,
+        NUM_DANCES * NUM_DANCE_ATTACKS,
+    )?
+    .chunks(NUM_DANCE_ATTACKS)
+    .zip(names.chunks(DANCE_NAME_SIZE))
+    .map(|(a, n)| Dance {
+        name: text::decode_name(n),
+        attacks: [a[0], a[1], a[2], a[3]],
+    })
+    .collect();
+
+    let background_dances = table(
+        rom_data,
+        rom_map::BATTLE_BG_DANCES,
+        rom_map::NUM_BATTLE_BACKGROUNDS,
+    )?
+    .to_vec();
+
+    let slots = table(rom_data, rom_map::SLOT_ATTACKS, SLOT_COMBOS.len())?
+        .iter()
+        .zip(SLOT_COMBOS.iter())
+        .map(|(s, c)| Slot {
+            combo: c.to_string(),
+            spell: *s,
+        })
+        .collect();
+
+    Ok(Abilities {
+        blitzes: blitzes,
+        swdtechs: swdtechs,
+        lores: lores,
+        rages: rages,
+        dances: dances,
+        background_dances: background_dances,
+        slots: slots,
+    })
+}
+
+fn spell_name(spells: &[Spell], i: u8) -> String {
+    match spells.get(i as usize) {
+        Some(s) if !s.name.is_empty() => s.name.clone(),
+        _ => format!("spell {:02x}", i),
+    }
+}
+
+// Lists every ability with spell, monster, and dance names resolved.
+pub fn to_text(abilities: &Abilities, spells: &[Spell], monsters: &[Monster]) -> String {
+    let mut text = String::from("Blitz:\n");
+    for b in &abilities.blitzes {
+        text.push_str(&format!(
+            "  {:<12} {}\n",
+            spell_name(spells, b.spell),
+            b.inputs().join(", ")
+        ));
+    }
+
+    text.push_str("SwdTech:\n");
+    for s in &abilities.swdtechs {
+        text.push_str(&format!(
+            "  {:<12} {}\n",
+            s.name,
+            spell_name(spells, s.spell)
+        ));
+    }
+
+    text.push_str("Lore:\n");
+    for l in &abilities.lores {
+        text.push_str(&format!("  {}\n", spell_name(spells, *l)));
+    }
+
+    text.push_str("Rage:\n");
+    for r in &abilities.rages {
+        let monster = monsters
+            .get(r.monster)
+            .map(|m| m.name.clone())
+            .unwrap_or_else(|| format!("monster {:03x}", r.monster));
+        text.push_str(&format!(
+            "  {:<12} {}, {}\n",
+            monster,
+            spell_name(spells, r.attacks[0]),
+            spell_name(spells, r.attacks[1])
+        ));
+    }
+
+    text.push_str("Dance:\n");
+    for d in &abilities.dances {
+        let attacks: Vec<String> = d.attacks.iter().map(|a| spell_name(spells, *a)).collect();
+        text.push_str(&format!("  {:<12} {}\n", d.name, attacks.join(", ")));
+    }
+    for (bg, d) in abilities.background_dances.iter().enumerate() {
+        let dance = abilities
+            .dances
+            .get(*d as usize)
+            .map(|d| d.name.clone())
+            .unwrap_or_else(|| String::from("-"));
+        text.push_str(&format!("  background {:02x}: {}\n", bg, dance));
+    }
+
+    text.push_str("Slot:\n");
+    for s in &abilities.slots {
+        text.push_str(&format!(
+            "  {:<12} {}\n",
+            s.combo,
+            spell_name(spells, s.spell)
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use battle::magic;
+
+    #[test]
+    fn parse_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let rages = rom_map::snes_to_file(rom_map::RAGE_ATTACKS);
+        rom[rages + 2..rages + 4].copy_from_slice(&[0xee, 0x08]);
+        let names = rom_map::snes_to_file(rom_map::DANCE_NAMES);
+        rom[names..names + DANCE_NAME_SIZE]
+            .copy_from_slice(&text::encode_name("Wind Song", 12).unwrap());
+        let dances = rom_map::snes_to_file(rom_map::BATTLE_BG_DANCES);
+        rom[dances + 3] = 0xff;
+        let blitzes = rom_map::snes_to_file(rom_map::BLITZ_CODES);
+        for b in rom[blitzes..blitzes + BLITZ_CODE_SIZE].iter_mut() {
+            *b = NO_BLITZ_INPUT;
+        }
+        rom[blitzes..blitzes + 4].copy_from_slice(&[0x0b, 0x07, 0x0b, 0x20]);
+
+        let mut lore = [0u8; 14];
+        lore[3] = 0x04;
+        let spells = vec![
+            magic::parse_spell(&[0u8; 14], "Fire", None).unwrap(),
+            magic::parse_spell(&lore, "Aqua Rake", None).unwrap(),
+        ];
+        let a = parse(&rom, &spells).unwrap();
+        assert_eq!(vec![1], a.lores);
+        assert_eq!(vec!["Left", "Right", "Left", "20"], a.blitzes[0].inputs());
+        assert_eq!(FIRST_BLITZ_SPELL + 7, a.blitzes[7].spell);
+        assert_eq!([0xee, 0x08], a.rages[1].attacks);
+        assert_eq!("Wind Song", a.dances[0].name);
+        assert_eq!(SLOT_COMBOS.len(), a.slots.len());
+
+        let text = to_text(&a, &spells, &[]);
+        assert!(text.contains("spell 5d     Left, Right, Left, 20\n"));
+        assert!(text.contains("Lore:\n  Aqua Rake\n"));
+        assert!(text.contains("monster 001  spell ee, spell 08"));
+        assert!(text.contains("background 00: Wind Song"));
+        assert!(text.contains("background 03: -"));
+    }
+}
@@ -0,0 +1,419 @@
+use nom::{le_u16, le_u8};
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::status;
+use rom_map;
+use utils::check_len;
+
+#[derive(Debug, PartialEq)]
+enum Tag {
+    RandomCast = 0xf0,
+    Target = 0xf1,
+    ChangeFormation = 0xf2,
+    Message = 0xf3,
+    RandomCommand = 0xf4,
+    Animate = 0xf5,
+    UseItem = 0xf6,
+    BattleEvent = 0xf7,
+    Variable = 0xf8,
+    Bits = 0xf9,
+    Special = 0xfa,
+    Misc = 0xfb,
+    If = 0xfc,
+    Wait = 0xfd,
+    EndIf = 0xfe,
+    End = 0xff,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Condition {
+    CommandUsed { commands: [u8; 2] },
+    SpellUsed { spells: [u8; 2] },
+    ItemUsed { items: [u8; 2] },
+    ElementUsed { elements: u8 },
+    AnyAttack,
+    // HP is compared against value * 128.  MP is compared against value.
+    HpBelow { target: u8, value: u8 },
+    MpBelow { target: u8, value: u8 },
+    HasStatus { target: u8, status: u8 },
+    LacksStatus { target: u8, status: u8 },
+    TimerAbove { value: u8 },
+    VariableBelow { var: u8, value: u8 },
+    VariableAtLeast { var: u8, value: u8 },
+    Other { kind: u8, args: [u8; 2] },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Action {
+    Cast { attack: u8 },
+    RandomCast { attacks: [u8; 3] },
+    Target { target: u8 },
+    ChangeFormation { args: [u8; 3] },
+    Message { msg: u16 },
+    RandomCommand { commands: [u8; 3] },
+    Animate { args: [u8; 3] },
+    UseItem { throw: bool, items: [u8; 2] },
+    BattleEvent { event: u8 },
+    Variable { var: u8, op: u8 },
+    Bits { args: [u8; 3] },
+    Special { args: [u8; 3] },
+    Misc { args: [u8; 2] },
+    If { condition: Condition },
+    Wait,
+    EndIf,
+}
+
+// The main section runs on the monster's turn and the counter section runs
+// when it is attacked.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Script {
+    pub main: Vec<Action>,
+    pub counter: Vec<Action>,
+}
+
+fn condition(kind: u8, a: u8, b: u8) -> Condition {
+    match kind {
+        0x01 => Condition::CommandUsed { commands: [a, b] },
+        0x02 => Condition::SpellUsed { spells: [a, b] },
+        0x03 => Condition::ItemUsed { items: [a, b] },
+        0x04 => Condition::ElementUsed { elements: a },
+        0x05 => Condition::AnyAttack,
+        0x06 => Condition::HpBelow {
+            target: a,
+            value: b,
+        },
+        0x07 => Condition::MpBelow {
+            target: a,
+            value: b,
+        },
+        0x08 => Condition::HasStatus {
+            target: a,
+            status: b,
+        },
+        0x09 => Condition::LacksStatus {
+            target: a,
+            status: b,
+        },
+        0x0b => Condition::TimerAbove { value: a },
+        0x0c => Condition::VariableBelow { var: a, value: b },
+        0x0d => Condition::VariableAtLeast { var: a, value: b },
+        _ => Condition::Other {
+            kind: kind,
+            args: [a, b],
+        },
+    }
+}
+
+macro_rules! parse_three {
+    ($i:expr, $tag:expr, $action:ident, $field:ident) => {
+        do_parse!(
+            $i,
+            tag!(&[$tag as u8]) >> v: take!(3) >> (Action::$action { $field: [v[0], v[1], v[2]] })
+        )
+    };
+}
+
+named!(parse_cast<&[u8], Action>,
+do_parse!(
+    a: verify!(le_u8, |a: u8| a < Tag::RandomCast as u8) >>
+    (Action::Cast{attack: a})
+));
+
+named!(parse_target<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::Target as u8]) >>
+    t: le_u8 >>
+    (Action::Target{target: t})
+));
+
+named!(parse_message<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::Message as u8]) >>
+    m: le_u16 >>
+    (Action::Message{msg: m})
+));
+
+named!(parse_use_item<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::UseItem as u8]) >>
+    v: take!(3) >>
+    (Action::UseItem{throw: v[0] != 0, items: [v[1], v[2]]})
+));
+
+named!(parse_battle_event<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::BattleEvent as u8]) >>
+    e: le_u8 >>
+    (Action::BattleEvent{event: e})
+));
+
+named!(parse_variable<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::Variable as u8]) >>
+    v: take!(2) >>
+    (Action::Variable{var: v[0], op: v[1]})
+));
+
+named!(parse_misc<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::Misc as u8]) >>
+    v: take!(2) >>
+    (Action::Misc{args: [v[0], v[1]]})
+));
+
+named!(parse_if<&[u8], Action>,
+do_parse!(
+    tag!(&[Tag::If as u8]) >>
+    v: take!(3) >>
+    (Action::If{condition: condition(v[0], v[1], v[2])})
+));
+
+named!(pub parse_action<&[u8], Action>, alt!(
+    parse_cast |
+    parse_three!(Tag::RandomCast, RandomCast, attacks) |
+    parse_target |
+    parse_three!(Tag::ChangeFormation, ChangeFormation, args) |
+    parse_message |
+    parse_three!(Tag::RandomCommand, RandomCommand, commands) |
+    parse_three!(Tag::Animate, Animate, args) |
+    parse_use_item |
+    parse_battle_event |
+    parse_variable |
+    parse_three!(Tag::Bits, Bits, args) |
+    parse_three!(Tag::Special, Special, args) |
+    parse_misc |
+    parse_if |
+    do_parse!(tag!(&[Tag::Wait as u8]) >> (Action::Wait)) |
+    do_parse!(tag!(&[Tag::EndIf as u8]) >> (Action::EndIf))
+));
+
+named!(parse_section<&[u8], Vec<Action> >, do_parse!(
+    actions: many_till!(parse_action, tag!(&[Tag::End as u8])) >>
+    (actions.0)));
+
+named!(pub parse_script<&[u8], Script>, do_parse!(
+    main: parse_section >>
+    counter: parse_section >>
+    (Script{main: main, counter: counter})));
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Script>, Box<Error>> {
+    let ptrs = rom_map::snes_to_file(rom_map::MONSTER_AI_POINTERS);
+    let base = rom_map::snes_to_file(rom_map::MONSTER_AI_SCRIPTS);
+    check_len(rom_data, ptrs, rom_map::NUM_MONSTERS * 2)?;
+
+    let mut scripts = Vec::new();
+    for i in 0..rom_map::NUM_MONSTERS {
+        let offset = rom_data[ptrs + i * 2] as usize | (rom_data[ptrs + i * 2 + 1] as usize) << 8;
+        let addr = base + offset;
+        if addr >= rom_data.len() {
+            return Err(SimpleError::new(format!("monster {} AI out of range", i)).into());
+        }
+        match parse_script(&rom_data[addr..]) {
+            Ok((_, script)) => scripts.push(script),
+            Err(_) => {
+                return Err(SimpleError::new(format!(
+                    "can't parse monster {} AI at 0x{:06x}",
+                    i, addr
+                ))
+                .into())
+            }
+        }
+    }
+    Ok(scripts)
+}
+
+fn name(names: &[String], i: u8) -> String {
+    match names.get(i as usize) {
+        Some(n) if !n.is_empty() => format!("{} (0x{:02x})", n, i),
+        _ => format!("0x{:02x}", i),
+    }
+}
+
+fn status_name(i: u8) -> &'static str {
+    status::NAMES.get(i as usize).cloned().unwrap_or("unknown")
+}
+
+pub fn condition_text(c: &Condition) -> String {
+    match c {
+        Condition::CommandUsed { commands } => {
+            format!(
+                "command 0x{:02x} or 0x{:02x} used",
+                commands[0], commands[1]
+            )
+        }
+        Condition::SpellUsed { spells } => {
+            format!("spell 0x{:02x} or 0x{:02x} used", spells[0], spells[1])
+        }
+        Condition::ItemUsed { items } => {
+            format!("item 0x{:02x} or 0x{:02x} used", items[0], items[1])
+        }
+        Condition::ElementUsed { elements } => format!("elements 0x{:02x} used", elements),
+        Condition::AnyAttack => "attacked".to_string(),
+        Condition::HpBelow { target, value } => {
+            format!("target 0x{:02x} hp < {}", target, *value as u32 * 128)
+        }
+        Condition::MpBelow { target, value } => {
+            format!("target 0x{:02x} mp < {}", target, value)
+        }
+        Condition::HasStatus { target, status } => {
+            format!("target 0x{:02x} has {}", target, status_name(*status))
+        }
+        Condition::LacksStatus { target, status } => {
+            format!("target 0x{:02x} lacks {}", target, status_name(*status))
+        }
+        Condition::TimerAbove { value } => format!("timer > {}", value),
+        Condition::VariableBelow { var, value } => format!("var {} < {}", var, value),
+        Condition::VariableAtLeast { var, value } => format!("var {} >= {}", var, value),
+        Condition::Other { kind, args } => format!(
+            "condition 0x{:02x} 0x{:02x} 0x{:02x}",
+            kind, args[0], args[1]
+        ),
+    }
+}
+
+pub fn action_text(a: &Action, attack_names: &[String]) -> String {
+    let three = |v: &[u8; 3]| {
+        v.iter()
+            .map(|i| name(attack_names, *i))
+            .collect::<Vec<String>>()
+            .join(" | ")
+    };
+    match a {
+        Action::Cast { attack } => format!("cast {}", name(attack_names, *attack)),
+        Action::RandomCast { attacks } => format!("cast one of {}", three(attacks)),
+        Action::Target { target } => format!("target 0x{:02x}", target),
+        Action::ChangeFormation { args } => format!(
+            "change formation 0x{:02x} 0x{:02x} 0x{:02x}",
+            args[0], args[1], args[2]
+        ),
+        Action::Message { msg } => format!("message 0x{:04x}", msg),
+        Action::RandomCommand { commands } => format!(
+            "command one of 0x{:02x} | 0x{:02x} | 0x{:02x}",
+            commands[0], commands[1], commands[2]
+        ),
+        Action::Animate { args } => format!(
+            "animate 0x{:02x} 0x{:02x} 0x{:02x}",
+            args[0], args[1], args[2]
+        ),
+        Action::UseItem { throw, items } => format!(
+            "{} item 0x{:02x} | 0x{:02x}",
+            if *throw { "throw" } else { "use" },
+            items[0],
+            items[1]
+        ),
+        Action::BattleEvent { event } => format!("battle event 0x{:02x}", event),
+        Action::Variable { var, op } => format!("var {} op 0x{:02x}", var, op),
+        Action::Bits { args } => {
+            format!("bits 0x{:02x} 0x{:02x} 0x{:02x}", args[0], args[1], args[2])
+        }
+        Action::Special { args } => format!(
+            "special 0x{:02x} 0x{:02x} 0x{:02x}",
+            args[0], args[1], args[2]
+        ),
+        Action::Misc { args } => format!("misc 0x{:02x} 0x{:02x}", args[0], args[1]),
+        Action::If { condition } => format!("if {}", condition_text(condition)),
+        Action::Wait => "wait".to_string(),
+        Action::EndIf => "end".to_string(),
+    }
+}
+
+// Conditions apply to the following actions until an EndIf.  Consecutive
+// conditions must all be true.
+fn section_text(text: &mut String, title: &str, actions: &[Action], attack_names: &[String]) {
+    text.push_str(&format!("{}:\n", title));
+    let mut in_if = false;
+    let mut prev_if = false;
+    for a in actions {
+        let line = action_text(a, attack_names);
+        match a {
+            Action::If { .. } if prev_if => {
+                text.push_str(&format!("  and{}\n", &line[2..]));
+            }
+            Action::If { .. } => {
+                text.push_str(&format!("  {}\n", line));
+                in_if = true;
+            }
+            Action::EndIf => {
+                text.push_str(&format!("  {}\n", line));
+                in_if = false;
+            }
+            _ => {
+                let indent = if in_if { "    " } else { "  " };
+                text.push_str(&format!("{}{}\n", indent, line));
+            }
+        }
+        prev_if = matches!(a, Action::If { .. });
+    }
+}
+
+pub fn to_text(script: &Script, attack_names: &[String]) -> String {
+    let mut text = String::new();
+    section_text(&mut text, "main", &script.main, attack_names);
+    section_text(&mut text, "counter", &script.counter, attack_names);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_actions_test() {
+        assert_eq!(
+            Action::Cast { attack: 0xee },
+            parse_action(&[0xee]).unwrap().1
+        );
+        assert_eq!(
+            Action::RandomCast {
+                attacks: [0xee, 0xee, 0x00]
+            },
+            parse_action(&[Tag::RandomCast as u8, 0xee, 0xee, 0x00])
+                .unwrap()
+                .1
+        );
+        assert_eq!(
+            Action::Message { msg: 0x0123 },
+            parse_action(&[Tag::Message as u8, 0x23, 0x01]).unwrap().1
+        );
+        assert_eq!(
+            Action::UseItem {
+                throw: true,
+                items: [0x10, 0x11]
+            },
+            parse_action(&[Tag::UseItem as u8, 0x01, 0x10, 0x11])
+                .unwrap()
+                .1
+        );
+        assert_eq!(
+            Action::If {
+                condition: Condition::HpBelow {
+                    target: 0x36,
+                    value: 10
+                }
+            },
+            parse_action(&[Tag::If as u8, 0x06, 0x36, 0x0a]).unwrap().1
+        );
+        assert!(parse_action(&[Tag::End as u8]).is_err());
+    }
+
+    #[test]
+    fn script_test() {
+        let data = [
+            0xfc, 0x06, 0x36, 0x0a, 0xfc, 0x08, 0x36, 0x07, 0xf0, 0x00, 0x01, 0x02, 0xfe, 0xee,
+            0xff, 0xfc, 0x05, 0x00, 0x00, 0xef, 0xff,
+        ];
+        let (rest, script) = parse_script(&data).unwrap();
+        assert_eq!(0, rest.len());
+        assert_eq!(5, script.main.len());
+        assert_eq!(2, script.counter.len());
+
+        let names = vec!["Fire".to_string(), "Ice".to_string()];
+        assert_eq!(
+            "main:\n  if target 0x36 hp < 1280\n  and target 0x36 has death\n    \
+             cast one of Fire (0x00) | Ice (0x01) | 0x02\n  end\n  cast 0xee\n\
+             counter:\n  if attacked\n    cast 0xef\n",
+            to_text(&script, &names)
+        );
+    }
+}
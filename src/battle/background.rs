@@ -0,0 +1,226 @@
+use simple_error::SimpleError;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use compression;
+use field::Location;
+use graphics::palette::{self, Kind};
+use graphics::tile::{self, Format, Tile, TILE_SIZE};
+use graphics::Image;
+use rom_map;
+use utils::check_len;
+
+const BACKGROUND_SIZE: usize = 5;
+const NUM_GRAPHICS_SETS: usize = 3;
+// Tilemaps are 32x32 SNES BG entries.
+const TILEMAP_SIZE: usize = 32;
+const SUB_PALETTE_SIZE: usize = 0x10;
+
+// Graphics set index of unused sets.
+pub const NO_GRAPHICS: u8 = 0xff;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Background {
+    pub graphics: [u8; NUM_GRAPHICS_SETS],
+    pub tilemap: u8,
+    pub palette: u8,
+}
+
+pub fn parse_background(data: &[u8]) -> Result<Background, Box<Error>> {
+    if data.len() < BACKGROUND_SIZE {
+        return Err(SimpleError::new("battle background data too short").into());
+    }
+    Ok(Background {
+        graphics: [data[0], data[1], data[2]],
+        tilemap: data[3],
+        palette: data[4],
+    })
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Background>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::BATTLE_BG_DATA);
+    check_len(
+        rom_data,
+        addr,
+        rom_map::NUM_BATTLE_BACKGROUNDS * BACKGROUND_SIZE,
+    )?;
+    let mut backgrounds = Vec::new();
+    for i in 0..rom_map::NUM_BATTLE_BACKGROUNDS {
+        backgrounds.push(parse_background(&rom_data[addr + i * BACKGROUND_SIZE..])?);
+    }
+    Ok(backgrounds)
+}
+
+// Graphics sets are compressed 4bpp tiles found through 24 bit pointers.
+pub fn graphics_set_addr(rom_data: &[u8], set: u8) -> Result<usize, Box<Error>> {
+    let ptr = rom_map::snes_to_file(rom_map::BATTLE_BG_GRAPHICS_POINTERS) + set as usize * 3;
+    check_len(rom_data, ptr, 3)?;
+    let snes_addr = rom_data[ptr] as usize
+        | (rom_data[ptr + 1] as usize) << 8
+        | (rom_data[ptr + 2] as usize) << 16;
+    if snes_addr < rom_map::SNES_ROM_ADDR {
+        return Err(SimpleError::new(format!(
+            "bad graphics set {:02x} pointer {:06x}",
+            set, snes_addr
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(snes_addr);
+    check_len(rom_data, addr, 2)?;
+    Ok(addr)
+}
+
+fn graphics_set(rom_data: &[u8], set: u8) -> Result<Vec<Tile>, Box<Error>> {
+    let addr = graphics_set_addr(rom_data, set)?;
+    Ok(tile::decode(
+        &compression::decompress(&rom_data[addr..])?,
+        Format::Bpp4,
+    ))
+}
+
+// Tilemaps are compressed and found through 16 bit pointers into their bank.
+pub fn tilemap_addr(rom_data: &[u8], index: u8) -> Result<usize, Box<Error>> {
+    let ptr = rom_map::snes_to_file(rom_map::BATTLE_BG_TILEMAP_POINTERS) + index as usize * 2;
+    check_len(rom_data, ptr, 2)?;
+    let addr = rom_map::snes_to_file(rom_map::BATTLE_BG_TILEMAP_BANK)
+        + (rom_data[ptr] as usize | (rom_data[ptr + 1] as usize) << 8);
+    check_len(rom_data, addr, 2)?;
+    Ok(addr)
+}
+
+fn tilemap(rom_data: &[u8], index: u8) -> Result<Vec<u16>, Box<Error>> {
+    let addr = tilemap_addr(rom_data, index)?;
+    let mut data = compression::decompress(&rom_data[addr..])?;
+    data.resize(TILEMAP_SIZE * TILEMAP_SIZE * 2, 0);
+    Ok(data
+        .chunks(2)
+        .map(|c| c[0] as u16 | (c[1] as u16) << 8)
+        .collect())
+}
+
+// Tilemap entries use the SNES BG format: a 10 bit tile index, a 3 bit
+// palette, priority, and flips.  Tiles from the graphics sets are numbered
+// consecutively.
+pub fn render(rom_data: &[u8], bg: &Background) -> Result<Image, Box<Error>> {
+    let mut tiles = Vec::new();
+    for set in bg.graphics.iter().filter(|g| **g != NO_GRAPHICS) {
+        tiles.extend(graphics_set(rom_data, *set)?);
+    }
+    let entries = tilemap(rom_data, bg.tilemap)?;
+    let palette = palette::read(rom_data, Kind::BattleBackground, bg.palette as usize)?;
+
+    let mut image = Image::new(TILEMAP_SIZE * TILE_SIZE, TILEMAP_SIZE * TILE_SIZE);
+    for (i, e) in entries.iter().enumerate() {
+        let t = (e & 0x3ff) as usize;
+        if t >= tiles.len() {
+            continue;
+        }
+        image.draw_tile(
+            &tiles[t],
+            palette.sub_palette(((e >> 10) & 0x7) as usize, SUB_PALETTE_SIZE),
+            (i % TILEMAP_SIZE) * TILE_SIZE,
+            (i / TILEMAP_SIZE) * TILE_SIZE,
+            e & 0x4000 != 0,
+            e & 0x8000 != 0,
+        );
+    }
+    Ok(image)
+}
+
+// Maps each battle background to the locations which use it.
+pub fn locations_by_background(locations: &[Location]) -> BTreeMap<u8, Vec<usize>> {
+    let mut users: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    for (l, location) in locations.iter().enumerate() {
+        users
+            .entry(location.properties.battle_background)
+            .or_default()
+            .push(l);
+    }
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::Color;
+
+    #[test]
+    fn parse_test() {
+        let b = parse_background(&[0x01, 0x32, 0xff, 0x05, 0x02]).unwrap();
+        assert_eq!([0x01, 0x32, NO_GRAPHICS], b.graphics);
+        assert_eq!(5, b.tilemap);
+        assert_eq!(2, b.palette);
+        assert!(parse_background(&[0x01]).is_err());
+    }
+
+    #[test]
+    fn render_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let mut put = |snes_addr: usize, data: &[u8]| {
+            let addr = rom_map::snes_to_file(snes_addr);
+            rom[addr..addr + data.len()].copy_from_slice(data);
+        };
+
+        // Set 1 is a solid tile of color 1.  Set 2 is a tile with a left
+        // column of color 2.
+        let mut column = [0u8; TILE_SIZE * TILE_SIZE];
+        for y in 0..TILE_SIZE {
+            column[y * TILE_SIZE] = 2;
+        }
+        let sets: [(u8, usize, Tile); 2] = [
+            (1, 0xe80000, [1u8; TILE_SIZE * TILE_SIZE]),
+            (2, 0xe81000, column),
+        ];
+        for (set, addr, t) in sets.iter() {
+            let data = tile::encode(&[*t], Format::Bpp4);
+            put(
+                *addr,
+                &compression::compress(&data, compression::Mode::Fast).unwrap(),
+            );
+            put(
+                rom_map::BATTLE_BG_GRAPHICS_POINTERS + *set as usize * 3,
+                &[*addr as u8, (*addr >> 8) as u8, (*addr >> 16) as u8],
+            );
+        }
+
+        // Tile 0, tile 1 with palette 2, tile 1 flipped, and a tile past the
+        // end of the sets.
+        let mut entries = Vec::new();
+        for e in [0x0000u16, 0x0801, 0x4001, 0x0005].iter() {
+            entries.push(*e as u8);
+            entries.push((*e >> 8) as u8);
+        }
+        put(
+            rom_map::BATTLE_BG_TILEMAP_BANK + 0x2000,
+            &compression::compress(&entries, compression::Mode::Fast).unwrap(),
+        );
+        put(rom_map::BATTLE_BG_TILEMAP_POINTERS + 3 * 2, &[0x00, 0x20]);
+
+        // Red and green in sub-palette 0 and blue in sub-palette 2.
+        let palette = rom_map::BATTLE_BG_PALETTES + Kind::BattleBackground.colors() * 2;
+        put(palette + 2, &[0x1f, 0x00, 0xe0, 0x03]);
+        put(palette + (2 * SUB_PALETTE_SIZE + 2) * 2, &[0x00, 0x7c]);
+
+        let bg = Background {
+            graphics: [1, 2, NO_GRAPHICS],
+            tilemap: 3,
+            palette: 1,
+        };
+        let image = render(&rom, &bg).unwrap();
+        let red = palette::decode_color(&[0x1f, 0x00]);
+        let green = palette::decode_color(&[0xe0, 0x03]);
+        let blue = palette::decode_color(&[0x00, 0x7c]);
+        assert_eq!(red, image.get_pixel(7, 7));
+        assert_eq!(blue, image.get_pixel(8, 3));
+        assert_eq!(Color::transparent(), image.get_pixel(9, 3));
+        assert_eq!(Color::transparent(), image.get_pixel(16, 3));
+        assert_eq!(green, image.get_pixel(23, 3));
+        assert_eq!(Color::transparent(), image.get_pixel(24, 0));
+
+        let bg = Background {
+            graphics: [4, NO_GRAPHICS, NO_GRAPHICS],
+            ..bg
+        };
+        assert!(render(&rom, &bg).is_err());
+    }
+}
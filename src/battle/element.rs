@@ -0,0 +1,77 @@
+use utils::test_bit;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Elements {
+    pub fire: bool,
+    pub ice: bool,
+    pub lightning: bool,
+    pub poison: bool,
+    pub wind: bool,
+    pub holy: bool,
+    pub earth: bool,
+    pub water: bool,
+}
+
+pub fn decode(b: u8) -> Elements {
+    Elements {
+        fire: test_bit(b, 0),
+        ice: test_bit(b, 1),
+        lightning: test_bit(b, 2),
+        poison: test_bit(b, 3),
+        wind: test_bit(b, 4),
+        holy: test_bit(b, 5),
+        earth: test_bit(b, 6),
+        water: test_bit(b, 7),
+    }
+}
+
+pub fn encode(e: &Elements) -> u8 {
+    [
+        e.fire,
+        e.ice,
+        e.lightning,
+        e.poison,
+        e.wind,
+        e.holy,
+        e.earth,
+        e.water,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |b, (i, set)| if *set { b | 1 << i } else { b })
+}
+
+impl Elements {
+    pub fn names(&self) -> Vec<&'static str> {
+        let names = [
+            "fire",
+            "ice",
+            "lightning",
+            "poison",
+            "wind",
+            "holy",
+            "earth",
+            "water",
+        ];
+        let b = encode(self);
+        names
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| test_bit(b, *i as u8))
+            .map(|(_, n)| *n)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let e = decode(0x85);
+        assert!(e.fire && e.lightning && e.water);
+        assert_eq!(vec!["fire", "lightning", "water"], e.names());
+        assert_eq!(0x85, encode(&e));
+    }
+}
@@ -0,0 +1,219 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use field::Location;
+use rom_map;
+use utils::check_len;
+
+const PACK_SIZE: usize = 8;
+const NUM_TERRAINS: usize = 4;
+// World maps are 256x256 tiles split into an 8x8 grid of zones.
+const ZONE_SIZE: usize = 32;
+const ZONE_COLUMNS: usize = 8;
+
+// Terrain types which pick a zone's formation pack and rate.
+pub const TERRAINS: [&str; NUM_TERRAINS] = ["grass", "forest", "desert", "dirt"];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum EncounterRate {
+    Normal,
+    Low,
+    High,
+    Never,
+}
+
+impl EncounterRate {
+    fn from_bits(b: u8) -> EncounterRate {
+        match b & 0x3 {
+            0 => EncounterRate::Normal,
+            1 => EncounterRate::Low,
+            2 => EncounterRate::High,
+            _ => EncounterRate::Never,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum World {
+    Balance,
+    Ruin,
+}
+
+// Four formations, one of which is picked at random for each encounter.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct FormationPack {
+    pub formations: [u16; 4],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LocationEncounters {
+    pub location: usize,
+    pub enabled: bool,
+    pub rate: EncounterRate,
+    pub pack: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ZoneEncounters {
+    pub world: World,
+    pub zone: usize,
+    // Indexed by terrain.
+    pub packs: [u8; NUM_TERRAINS],
+    pub rates: [EncounterRate; NUM_TERRAINS],
+}
+
+pub fn parse_formation_pack(data: &[u8]) -> FormationPack {
+    let mut formations = [0u16; 4];
+    for (i, f) in formations.iter_mut().enumerate() {
+        *f = data[i * 2] as u16 | (data[i * 2 + 1] as u16) << 8;
+    }
+    FormationPack {
+        formations: formations,
+    }
+}
+
+pub fn formation_packs(rom_data: &[u8]) -> Result<Vec<FormationPack>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::FORMATION_PACKS);
+    check_len(rom_data, addr, rom_map::NUM_FORMATION_PACKS * PACK_SIZE)?;
+    Ok(
+        rom_data[addr..addr + rom_map::NUM_FORMATION_PACKS * PACK_SIZE]
+            .chunks(PACK_SIZE)
+            .map(parse_formation_pack)
+            .collect(),
+    )
+}
+
+pub fn encode_formation_pack(pack: &FormationPack) -> Vec<u8> {
+    let mut data = Vec::new();
+    for f in pack.formations.iter() {
+        data.push(*f as u8);
+        data.push((f >> 8) as u8);
+    }
+    data
+}
+
+pub fn write_formation_packs(
+    rom_data: &mut [u8],
+    packs: &[FormationPack],
+) -> Result<(), Box<Error>> {
+    if packs.len() != rom_map::NUM_FORMATION_PACKS {
+        return Err(SimpleError::new(format!(
+            "expected {} formation packs.  Got {}.",
+            rom_map::NUM_FORMATION_PACKS,
+            packs.len()
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(rom_map::FORMATION_PACKS);
+    check_len(rom_data, addr, rom_map::NUM_FORMATION_PACKS * PACK_SIZE)?;
+    for (i, p) in packs.iter().enumerate() {
+        let start = addr + i * PACK_SIZE;
+        rom_data[start..start + PACK_SIZE].copy_from_slice(&encode_formation_pack(p));
+    }
+    Ok(())
+}
+
+// Location rates are packed four to a byte.
+pub fn location_encounters(
+    rom_data: &[u8],
+    locations: &[Location],
+) -> Result<Vec<LocationEncounters>, Box<Error>> {
+    let packs = rom_map::snes_to_file(rom_map::LOCATION_ENCOUNTER_PACKS);
+    let rates = rom_map::snes_to_file(rom_map::LOCATION_ENCOUNTER_RATES);
+    check_len(rom_data, packs, locations.len())?;
+    check_len(rom_data, rates, locations.len().div_ceil(4))?;
+
+    Ok(locations
+        .iter()
+        .enumerate()
+        .map(|(l, location)| LocationEncounters {
+            location: l,
+            enabled: location.properties.enable_random_battles,
+            rate: EncounterRate::from_bits(rom_data[rates + l / 4] >> ((l % 4) * 2)),
+            pack: rom_data[packs + l],
+        })
+        .collect())
+}
+
+pub fn zone_encounters(rom_data: &[u8], world: World) -> Result<Vec<ZoneEncounters>, Box<Error>> {
+    let index = match world {
+        World::Balance => 0,
+        World::Ruin => 1,
+    };
+    let packs = rom_map::snes_to_file(rom_map::WORLD_ENCOUNTER_PACKS)
+        + index * rom_map::NUM_WORLD_ZONES * NUM_TERRAINS;
+    let rates =
+        rom_map::snes_to_file(rom_map::WORLD_ENCOUNTER_RATES) + index * rom_map::NUM_WORLD_ZONES;
+    check_len(rom_data, packs, rom_map::NUM_WORLD_ZONES * NUM_TERRAINS)?;
+    check_len(rom_data, rates, rom_map::NUM_WORLD_ZONES)?;
+
+    let mut zones = Vec::new();
+    for zone in 0..rom_map::NUM_WORLD_ZONES {
+        let mut z = ZoneEncounters {
+            world: world,
+            zone: zone,
+            packs: [0; NUM_TERRAINS],
+            rates: [EncounterRate::Normal; NUM_TERRAINS],
+        };
+        for t in 0..NUM_TERRAINS {
+            z.packs[t] = rom_data[packs + zone * NUM_TERRAINS + t];
+            z.rates[t] = EncounterRate::from_bits(rom_data[rates + zone] >> (t * 2));
+        }
+        zones.push(z);
+    }
+    Ok(zones)
+}
+
+// Zone containing world map tile x, y.
+pub fn zone_at(x: usize, y: usize) -> usize {
+    (y / ZONE_SIZE) * ZONE_COLUMNS + x / ZONE_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use field::properties;
+
+    #[test]
+    fn encounters_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let packs = rom_map::snes_to_file(rom_map::FORMATION_PACKS);
+        rom[packs + 8..packs + 16]
+            .copy_from_slice(&[0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x80]);
+        assert_eq!(
+            [1, 2, 3, 0x8004],
+            formation_packs(&rom).unwrap()[1].formations
+        );
+        let mut p = formation_packs(&rom).unwrap();
+        p[2].formations[3] = 0x23f;
+        write_formation_packs(&mut rom, &p).unwrap();
+        assert_eq!([0x3f, 0x02], rom[packs + 22..packs + 24]);
+        assert_eq!(p, formation_packs(&rom).unwrap());
+
+        let location = Location {
+            properties: properties::test_properties(),
+            entrance_event_addr: 0,
+            triggers: Vec::new(),
+            npcs: Vec::new(),
+            short_exits: Vec::new(),
+            long_exits: Vec::new(),
+            treasures: Vec::new(),
+        };
+        let locations = vec![location; 6];
+        rom[rom_map::snes_to_file(rom_map::LOCATION_ENCOUNTER_PACKS) + 5] = 0x21;
+        rom[rom_map::snes_to_file(rom_map::LOCATION_ENCOUNTER_RATES) + 1] = 0x08;
+        let e = &location_encounters(&rom, &locations).unwrap()[5];
+        assert_eq!(0x21, e.pack);
+        assert_eq!(EncounterRate::High, e.rate);
+
+        let zones = rom_map::snes_to_file(rom_map::WORLD_ENCOUNTER_PACKS) + 0x100;
+        rom[zones + 9 * 4 + 2] = 0x33;
+        rom[rom_map::snes_to_file(rom_map::WORLD_ENCOUNTER_RATES) + 0x40 + 9] = 0x30;
+        let z = &zone_encounters(&rom, World::Ruin).unwrap()[9];
+        assert_eq!([0, 0, 0x33, 0], z.packs);
+        assert_eq!(EncounterRate::Never, z.rates[2]);
+        assert_eq!(EncounterRate::Normal, z.rates[1]);
+
+        assert_eq!(9, zone_at(40, 32));
+    }
+}
@@ -0,0 +1,159 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use rom_map;
+use utils::check_len;
+
+const ESPER_SIZE: usize = 11;
+const NUM_LEARNABLE: usize = 5;
+
+// Spell index of unused learnable slots and bonus index of espers without a
+// level up bonus.
+pub const NONE: u8 = 0xff;
+
+// Esper names are the spell names starting at this spell index.
+pub const FIRST_ESPER_SPELL: usize = 0x36;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Learnable {
+    pub spell: u8,
+    // Multiplied by the magic points earned in battle.
+    pub rate: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Esper {
+    pub spells: [Learnable; NUM_LEARNABLE],
+    pub bonus: u8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Bonus {
+    // Percentages added to the level up HP or MP gain.
+    Hp(u8),
+    Mp(u8),
+    Vigor(u8),
+    Speed(u8),
+    Stamina(u8),
+    MagicPower(u8),
+}
+
+impl Esper {
+    pub fn bonus(&self) -> Option<Bonus> {
+        decode_bonus(self.bonus)
+    }
+}
+
+pub fn decode_bonus(b: u8) -> Option<Bonus> {
+    match b {
+        0x00 => Some(Bonus::Hp(10)),
+        0x01 => Some(Bonus::Hp(30)),
+        0x02 => Some(Bonus::Hp(50)),
+        0x03 => Some(Bonus::Mp(10)),
+        0x04 => Some(Bonus::Mp(30)),
+        0x05 => Some(Bonus::Mp(50)),
+        0x06 => Some(Bonus::Hp(100)),
+        0x09 => Some(Bonus::Vigor(1)),
+        0x0a => Some(Bonus::Vigor(2)),
+        0x0b => Some(Bonus::Speed(1)),
+        0x0c => Some(Bonus::Speed(2)),
+        0x0d => Some(Bonus::Stamina(1)),
+        0x0e => Some(Bonus::Stamina(2)),
+        0x0f => Some(Bonus::MagicPower(1)),
+        0x10 => Some(Bonus::MagicPower(2)),
+        _ => None,
+    }
+}
+
+pub fn parse_esper(data: &[u8]) -> Result<Esper, Box<Error>> {
+    if data.len() < ESPER_SIZE {
+        return Err(SimpleError::new("esper data too short").into());
+    }
+    let mut spells = [Learnable {
+        spell: NONE,
+        rate: 0,
+    }; NUM_LEARNABLE];
+    for (i, s) in spells.iter_mut().enumerate() {
+        s.rate = data[i * 2];
+        s.spell = data[i * 2 + 1];
+    }
+    Ok(Esper {
+        spells: spells,
+        bonus: data[10],
+    })
+}
+
+pub fn encode_esper(e: &Esper) -> Vec<u8> {
+    let mut data = Vec::new();
+    for s in e.spells.iter() {
+        data.push(s.rate);
+        data.push(s.spell);
+    }
+    data.push(e.bonus);
+    data
+}
+
+fn table_addr(rom_data: &[u8]) -> Result<usize, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::ESPER_DATA);
+    check_len(rom_data, addr, rom_map::NUM_ESPERS * ESPER_SIZE)?;
+    Ok(addr)
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Esper>, Box<Error>> {
+    let addr = table_addr(rom_data)?;
+    let mut espers = Vec::new();
+    for i in 0..rom_map::NUM_ESPERS {
+        espers.push(parse_esper(&rom_data[addr + i * ESPER_SIZE..])?);
+    }
+    Ok(espers)
+}
+
+pub fn write(rom_data: &mut [u8], espers: &[Esper]) -> Result<(), Box<Error>> {
+    if espers.len() != rom_map::NUM_ESPERS {
+        return Err(SimpleError::new(format!(
+            "expected {} espers.  Got {}.",
+            rom_map::NUM_ESPERS,
+            espers.len()
+        ))
+        .into());
+    }
+    let addr = table_addr(rom_data)?;
+    for (i, e) in espers.iter().enumerate() {
+        let start = addr + i * ESPER_SIZE;
+        rom_data[start..start + ESPER_SIZE].copy_from_slice(&encode_esper(e));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        // Ramuh: Bolt x10, Bolt 2 x2, Poison x5, and Stamina +1.
+        let data = [
+            0x0a, 0x02, 0x02, 0x07, 0x05, 0x03, 0x00, 0xff, 0x00, 0xff, 0x0d,
+        ];
+        let e = parse_esper(&data).unwrap();
+        assert_eq!(
+            Learnable {
+                spell: 0x02,
+                rate: 10
+            },
+            e.spells[0]
+        );
+        assert_eq!(
+            Learnable {
+                spell: 0x07,
+                rate: 2
+            },
+            e.spells[1]
+        );
+        assert_eq!(NONE, e.spells[3].spell);
+        assert_eq!(Some(Bonus::Stamina(1)), e.bonus());
+        assert_eq!(None, decode_bonus(NONE));
+        assert_eq!(data.to_vec(), encode_esper(&e));
+        assert_eq!(Some(Bonus::Stamina(2)), decode_bonus(0x0e));
+    }
+}
@@ -0,0 +1,281 @@
+use simple_error::SimpleError;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use battle::encounter::{FormationPack, LocationEncounters, World, ZoneEncounters, TERRAINS};
+use field::treasure;
+use field::Location;
+use rom_map;
+use utils::check_len;
+
+const FORMATION_SIZE: usize = 15;
+const AUX_SIZE: usize = 4;
+const NUM_SLOTS: usize = 6;
+
+// Monster index of an unused slot.
+pub const NO_MONSTER: u16 = 0x1ff;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Slot {
+    pub monster: u16,
+    // Position in 8 pixel units.
+    pub x: u8,
+    pub y: u8,
+    // Hidden monsters are brought in later by AI or battle events.
+    pub visible: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Formation {
+    pub unknown_0: u8,
+    pub slots: Vec<Slot>,
+    pub unknown_e_bits: u8,
+
+    // Bit set for each of normal, back, pincer, and side attacks which can't
+    // happen.
+    pub disabled_attack_types: u8,
+    pub unknown_aux_0: u8,
+    pub aux_flags: u8,
+    pub special_event: u8,
+    // 0 plays the default battle theme.
+    pub music: u8,
+    pub unknown_aux_3: u8,
+}
+
+impl Formation {
+    pub fn monsters(&self) -> Vec<u16> {
+        self.slots
+            .iter()
+            .filter(|s| s.monster != NO_MONSTER)
+            .map(|s| s.monster)
+            .collect()
+    }
+}
+
+// Where a formation can be fought.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub enum Reference {
+    Location {
+        location: usize,
+        pack: u8,
+    },
+    Zone {
+        world: World,
+        zone: usize,
+        terrain: usize,
+        pack: u8,
+    },
+    Treasure {
+        location: usize,
+        index: usize,
+        pack: u8,
+    },
+}
+
+pub fn parse_formation(data: &[u8], aux: &[u8]) -> Result<Formation, Box<Error>> {
+    if data.len() < FORMATION_SIZE || aux.len() < AUX_SIZE {
+        return Err(SimpleError::new("formation data too short").into());
+    }
+
+    let slots = (0..NUM_SLOTS)
+        .map(|i| Slot {
+            monster: data[2 + i] as u16 | ((data[0xe] >> i) as u16 & 0x1) << 8,
+            x: data[8 + i] >> 4,
+            y: data[8 + i] & 0xf,
+            visible: (data[1] >> i) & 0x1 == 0x1,
+        })
+        .collect();
+    Ok(Formation {
+        unknown_0: data[0],
+        slots: slots,
+        unknown_e_bits: data[0xe] >> 6,
+        disabled_attack_types: aux[0] >> 4,
+        unknown_aux_0: aux[0] & 0xf,
+        aux_flags: aux[1],
+        special_event: aux[2],
+        music: (aux[3] >> 3) & 0x7,
+        unknown_aux_3: aux[3] & 0xc7,
+    })
+}
+
+// Returns the formation and auxiliary records.
+pub fn encode_formation(f: &Formation) -> Result<(Vec<u8>, Vec<u8>), Box<Error>> {
+    if f.slots.len() != NUM_SLOTS {
+        return Err(SimpleError::new(format!(
+            "formations have {} slots.  Formation has {}.",
+            NUM_SLOTS,
+            f.slots.len()
+        ))
+        .into());
+    }
+    let mut data = vec![0u8; FORMATION_SIZE];
+    data[0] = f.unknown_0;
+    data[0xe] = f.unknown_e_bits << 6;
+    for (i, s) in f.slots.iter().enumerate() {
+        if s.visible {
+            data[1] |= 1 << i;
+        }
+        data[2 + i] = s.monster as u8;
+        data[8 + i] = (s.x & 0xf) << 4 | s.y & 0xf;
+        data[0xe] |= ((s.monster >> 8) as u8 & 0x1) << i;
+    }
+    let aux = vec![
+        f.disabled_attack_types << 4 | f.unknown_aux_0 & 0xf,
+        f.aux_flags,
+        f.special_event,
+        (f.music & 0x7) << 3 | f.unknown_aux_3 & 0xc7,
+    ];
+    Ok((data, aux))
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Formation>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::FORMATIONS);
+    let aux_addr = rom_map::snes_to_file(rom_map::FORMATION_AUX);
+    check_len(rom_data, addr, rom_map::NUM_FORMATIONS * FORMATION_SIZE)?;
+    check_len(rom_data, aux_addr, rom_map::NUM_FORMATIONS * AUX_SIZE)?;
+
+    let mut formations = Vec::new();
+    for i in 0..rom_map::NUM_FORMATIONS {
+        formations.push(parse_formation(
+            &rom_data[addr + i * FORMATION_SIZE..],
+            &rom_data[aux_addr + i * AUX_SIZE..],
+        )?);
+    }
+    Ok(formations)
+}
+
+pub fn write(rom_data: &mut [u8], formations: &[Formation]) -> Result<(), Box<Error>> {
+    if formations.len() != rom_map::NUM_FORMATIONS {
+        return Err(SimpleError::new(format!(
+            "expected {} formations.  Got {}.",
+            rom_map::NUM_FORMATIONS,
+            formations.len()
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(rom_map::FORMATIONS);
+    let aux_addr = rom_map::snes_to_file(rom_map::FORMATION_AUX);
+    check_len(rom_data, addr, rom_map::NUM_FORMATIONS * FORMATION_SIZE)?;
+    check_len(rom_data, aux_addr, rom_map::NUM_FORMATIONS * AUX_SIZE)?;
+
+    for (i, f) in formations.iter().enumerate() {
+        let (data, aux) = encode_formation(f)?;
+        let start = addr + i * FORMATION_SIZE;
+        rom_data[start..start + FORMATION_SIZE].copy_from_slice(&data);
+        let start = aux_addr + i * AUX_SIZE;
+        rom_data[start..start + AUX_SIZE].copy_from_slice(&aux);
+    }
+    Ok(())
+}
+
+// Maps each formation to the location encounters, world map zones, and
+// monster-in-a-box chests that can start it.  Battles started by event scripts
+// aren't included.  Pack formation indexes are masked to their low 15 bits.
+pub fn references(
+    packs: &[FormationPack],
+    locations: &[Location],
+    location_encounters: &[LocationEncounters],
+    zones: &[ZoneEncounters],
+) -> BTreeMap<u16, Vec<Reference>> {
+    let mut refs: BTreeMap<u16, Vec<Reference>> = BTreeMap::new();
+    let mut add = |pack: u8, r: Reference| {
+        if let Some(p) = packs.get(pack as usize) {
+            for f in p.formations.iter() {
+                let list = refs.entry(f & 0x7fff).or_default();
+                if !list.contains(&r) {
+                    list.push(r.clone());
+                }
+            }
+        }
+    };
+
+    for e in location_encounters.iter().filter(|e| e.enabled) {
+        add(
+            e.pack,
+            Reference::Location {
+                location: e.location,
+                pack: e.pack,
+            },
+        );
+    }
+    for z in zones {
+        for t in 0..TERRAINS.len() {
+            add(
+                z.packs[t],
+                Reference::Zone {
+                    world: z.world,
+                    zone: z.zone,
+                    terrain: t,
+                    pack: z.packs[t],
+                },
+            );
+        }
+    }
+    for (l, location) in locations.iter().enumerate() {
+        for (i, t) in location.treasures.iter().enumerate() {
+            if t.kind == treasure::Kind::Monster {
+                add(
+                    t.amount,
+                    Reference::Treasure {
+                        location: l,
+                        index: i,
+                        pack: t.amount,
+                    },
+                );
+            }
+        }
+    }
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        let data = [
+            0x00, 0x03, 0x0c, 0x0d, 0xff, 0xff, 0xff, 0xff, 0x48, 0x88, 0x00, 0x00, 0x00, 0x00,
+            0x3c,
+        ];
+        let aux = [0x30, 0x00, 0x00, 0x08];
+        let f = parse_formation(&data, &aux).unwrap();
+        assert_eq!(vec![0x00c, 0x00d], f.monsters());
+        assert_eq!((4, 8), (f.slots[0].x, f.slots[0].y));
+        assert!(f.slots[1].visible);
+        assert!(!f.slots[2].visible);
+        assert_eq!(3, f.disabled_attack_types);
+        assert_eq!(1, f.music);
+
+        let (d, a) = encode_formation(&f).unwrap();
+        assert_eq!(data.to_vec(), d);
+        assert_eq!(aux.to_vec(), a);
+    }
+
+    #[test]
+    fn references_test() {
+        let packs = vec![
+            FormationPack {
+                formations: [1, 1, 2, 2],
+            },
+            FormationPack {
+                formations: [3, 3, 3, 0x8003],
+            },
+        ];
+        let encounters = vec![LocationEncounters {
+            location: 5,
+            enabled: true,
+            rate: ::battle::encounter::EncounterRate::Normal,
+            pack: 1,
+        }];
+        let refs = references(&packs, &[], &encounters, &[]);
+        assert_eq!(
+            vec![Reference::Location {
+                location: 5,
+                pack: 1
+            }],
+            refs[&3]
+        );
+        assert!(!refs.contains_key(&1));
+    }
+}
@@ -0,0 +1,318 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::element::{self, Elements};
+use battle::status::{self, Status};
+use rom_map;
+use text;
+use utils::{check_len, test_bit};
+
+const SPELL_SIZE: usize = 14;
+const NATURAL_MAGIC_SIZE: usize = 2;
+const NUM_NATURAL_MAGIC: usize = 16;
+
+// Spells are split across three name tables.  Magic names start with an
+// icon byte which is kept in `Spell.icon`.
+const NUM_MAGIC: usize = 0x36;
+const NUM_ESPER_ATTACKS: usize = 0x1b;
+const MAGIC_NAME_SIZE: usize = 7;
+const ESPER_NAME_SIZE: usize = 8;
+const ATTACK_NAME_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Spell {
+    pub name: String,
+    pub icon: Option<u8>,
+    pub targeting: u8,
+    pub element: Elements,
+
+    pub physical: bool,
+    pub miss_if_protected: bool,
+    pub only_dead_targets: bool,
+    pub invert_on_undead: bool,
+    pub random_target: bool,
+    pub ignore_defense: bool,
+    pub no_split_damage: bool,
+    pub abort_on_allies: bool,
+
+    pub field_usable: bool,
+    pub unreflectable: bool,
+    pub lore: bool,
+    pub runic: bool,
+    pub unknown_flag_3_4: bool,
+    pub retarget_if_dead: bool,
+    pub kills_user: bool,
+    pub mp_damage: bool,
+
+    pub heal: bool,
+    pub drain: bool,
+    pub lift_status: bool,
+    pub toggle_status: bool,
+    pub stamina_evade: bool,
+    pub undodgeable: bool,
+    pub level_multiple: bool,
+    pub fractional_damage: bool,
+
+    pub mp_cost: u8,
+    pub power: u8,
+    pub unknown_flags_7: u8,
+    pub hit_rate: u8,
+    pub special_effect: u8,
+    pub status: Status,
+}
+
+// A spell Terra or Celes learn on reaching `level`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct NaturalMagic {
+    pub spell: u8,
+    pub level: u8,
+}
+
+fn bits(flags: &[bool]) -> u8 {
+    flags
+        .iter()
+        .enumerate()
+        .fold(0, |b, (i, set)| if *set { b | 1 << i } else { b })
+}
+
+pub fn parse_spell(data: &[u8], name: &str, icon: Option<u8>) -> Result<Spell, Box<Error>> {
+    if data.len() < SPELL_SIZE {
+        return Err(SimpleError::new("spell data too short").into());
+    }
+
+    Ok(Spell {
+        name: name.to_string(),
+        icon: icon,
+        targeting: data[0x0],
+        element: element::decode(data[0x1]),
+
+        physical: test_bit(data[0x2], 0),
+        miss_if_protected: test_bit(data[0x2], 1),
+        only_dead_targets: test_bit(data[0x2], 2),
+        invert_on_undead: test_bit(data[0x2], 3),
+        random_target: test_bit(data[0x2], 4),
+        ignore_defense: test_bit(data[0x2], 5),
+        no_split_damage: test_bit(data[0x2], 6),
+        abort_on_allies: test_bit(data[0x2], 7),
+
+        field_usable: test_bit(data[0x3], 0),
+        unreflectable: test_bit(data[0x3], 1),
+        lore: test_bit(data[0x3], 2),
+        runic: test_bit(data[0x3], 3),
+        unknown_flag_3_4: test_bit(data[0x3], 4),
+        retarget_if_dead: test_bit(data[0x3], 5),
+        kills_user: test_bit(data[0x3], 6),
+        mp_damage: test_bit(data[0x3], 7),
+
+        heal: test_bit(data[0x4], 0),
+        drain: test_bit(data[0x4], 1),
+        lift_status: test_bit(data[0x4], 2),
+        toggle_status: test_bit(data[0x4], 3),
+        stamina_evade: test_bit(data[0x4], 4),
+        undodgeable: test_bit(data[0x4], 5),
+        level_multiple: test_bit(data[0x4], 6),
+        fractional_damage: test_bit(data[0x4], 7),
+
+        mp_cost: data[0x5],
+        power: data[0x6],
+        unknown_flags_7: data[0x7],
+        hit_rate: data[0x8],
+        special_effect: data[0x9],
+        status: status::decode(&data[0xa..0xe]),
+    })
+}
+
+pub fn encode_spell(s: &Spell) -> Vec<u8> {
+    let mut data = vec![
+        s.targeting,
+        element::encode(&s.element),
+        bits(&[
+            s.physical,
+            s.miss_if_protected,
+            s.only_dead_targets,
+            s.invert_on_undead,
+            s.random_target,
+            s.ignore_defense,
+            s.no_split_damage,
+            s.abort_on_allies,
+        ]),
+        bits(&[
+            s.field_usable,
+            s.unreflectable,
+            s.lore,
+            s.runic,
+            s.unknown_flag_3_4,
+            s.retarget_if_dead,
+            s.kills_user,
+            s.mp_damage,
+        ]),
+        bits(&[
+            s.heal,
+            s.drain,
+            s.lift_status,
+            s.toggle_status,
+            s.stamina_evade,
+            s.undodgeable,
+            s.level_multiple,
+            s.fractional_damage,
+        ]),
+        s.mp_cost,
+        s.power,
+        s.unknown_flags_7,
+        s.hit_rate,
+        s.special_effect,
+    ];
+    data.extend_from_slice(&status::encode(&s.status));
+    data
+}
+
+// File offset and length of spell `i`'s name.
+fn name_location(i: usize) -> (usize, usize) {
+    if i < NUM_MAGIC {
+        (
+            rom_map::snes_to_file(rom_map::MAGIC_NAMES) + i * MAGIC_NAME_SIZE,
+            MAGIC_NAME_SIZE,
+        )
+    } else if i < NUM_MAGIC + NUM_ESPER_ATTACKS {
+        (
+            rom_map::snes_to_file(rom_map::ESPER_NAMES) + (i - NUM_MAGIC) * ESPER_NAME_SIZE,
+            ESPER_NAME_SIZE,
+        )
+    } else {
+        (
+            rom_map::snes_to_file(rom_map::ATTACK_NAMES)
+                + (i - NUM_MAGIC - NUM_ESPER_ATTACKS) * ATTACK_NAME_SIZE,
+            ATTACK_NAME_SIZE,
+        )
+    }
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Spell>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::SPELL_DATA);
+    check_len(rom_data, addr, rom_map::NUM_SPELLS * SPELL_SIZE)?;
+
+    let mut spells = Vec::new();
+    for i in 0..rom_map::NUM_SPELLS {
+        let (name_addr, len) = name_location(i);
+        check_len(rom_data, name_addr, len)?;
+        let name = &rom_data[name_addr..name_addr + len];
+        let spell = if i < NUM_MAGIC {
+            parse_spell(
+                &rom_data[addr + i * SPELL_SIZE..],
+                &text::decode_name(&name[1..]),
+                Some(name[0]),
+            )?
+        } else {
+            parse_spell(
+                &rom_data[addr + i * SPELL_SIZE..],
+                &text::decode_name(name),
+                None,
+            )?
+        };
+        spells.push(spell);
+    }
+    Ok(spells)
+}
+
+pub fn write(rom_data: &mut [u8], spells: &[Spell]) -> Result<(), Box<Error>> {
+    if spells.len() != rom_map::NUM_SPELLS {
+        return Err(SimpleError::new(format!(
+            "expected {} spells.  Got {}.",
+            rom_map::NUM_SPELLS,
+            spells.len()
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(rom_map::SPELL_DATA);
+    check_len(rom_data, addr, rom_map::NUM_SPELLS * SPELL_SIZE)?;
+
+    for (i, s) in spells.iter().enumerate() {
+        let start = addr + i * SPELL_SIZE;
+        rom_data[start..start + SPELL_SIZE].copy_from_slice(&encode_spell(s));
+
+        let (name_addr, len) = name_location(i);
+        check_len(rom_data, name_addr, len)?;
+        let name = if i < NUM_MAGIC {
+            let mut name = vec![s.icon.unwrap_or(0xff)];
+            name.extend(text::encode_name(&s.name, len - 1)?);
+            name
+        } else {
+            text::encode_name(&s.name, len)?
+        };
+        rom_data[name_addr..name_addr + len].copy_from_slice(&name);
+    }
+    Ok(())
+}
+
+fn natural_magic_addr(character: usize) -> Result<usize, Box<Error>> {
+    match character {
+        0 => Ok(rom_map::snes_to_file(rom_map::TERRA_NATURAL_MAGIC)),
+        1 => Ok(rom_map::snes_to_file(rom_map::CELES_NATURAL_MAGIC)),
+        _ => Err(SimpleError::new(format!("no natural magic for character {}", character)).into()),
+    }
+}
+
+// `character` is 0 for Terra and 1 for Celes.
+pub fn natural_magic(rom_data: &[u8], character: usize) -> Result<Vec<NaturalMagic>, Box<Error>> {
+    let addr = natural_magic_addr(character)?;
+    check_len(rom_data, addr, NUM_NATURAL_MAGIC * NATURAL_MAGIC_SIZE)?;
+    Ok(
+        rom_data[addr..addr + NUM_NATURAL_MAGIC * NATURAL_MAGIC_SIZE]
+            .chunks(NATURAL_MAGIC_SIZE)
+            .map(|c| NaturalMagic {
+                spell: c[0],
+                level: c[1],
+            })
+            .collect(),
+    )
+}
+
+pub fn write_natural_magic(
+    rom_data: &mut [u8],
+    character: usize,
+    magic: &[NaturalMagic],
+) -> Result<(), Box<Error>> {
+    if magic.len() != NUM_NATURAL_MAGIC {
+        return Err(SimpleError::new(format!(
+            "expected {} natural magic entries.  Got {}.",
+            NUM_NATURAL_MAGIC,
+            magic.len()
+        ))
+        .into());
+    }
+    let addr = natural_magic_addr(character)?;
+    check_len(rom_data, addr, NUM_NATURAL_MAGIC * NATURAL_MAGIC_SIZE)?;
+    for (i, m) in magic.iter().enumerate() {
+        rom_data[addr + i * NATURAL_MAGIC_SIZE] = m.spell;
+        rom_data[addr + i * NATURAL_MAGIC_SIZE + 1] = m.level;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        // Fire
+        let data = [
+            0x21, 0x01, 0x00, 0x28, 0x00, 0x04, 0x15, 0x00, 0x96, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let s = parse_spell(&data, "Fire", Some(0xe8)).unwrap();
+        assert!(s.element.fire);
+        assert!(s.runic && s.retarget_if_dead);
+        assert_eq!(4, s.mp_cost);
+        assert_eq!(21, s.power);
+        assert_eq!(data.to_vec(), encode_spell(&s));
+
+        let mut rom = vec![0u8; 0x400000];
+        let mut spells = vec![s; rom_map::NUM_SPELLS];
+        for s in spells[NUM_MAGIC..].iter_mut() {
+            s.icon = None;
+        }
+        spells[0x40].name = String::from("Ifrit");
+        write(&mut rom, &spells).unwrap();
+        assert_eq!(spells, parse(&rom).unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+use std::error::Error;
+
+pub mod abilities;
+pub mod ai;
+pub mod background;
+pub mod element;
+pub mod encounter;
+pub mod esper;
+pub mod formation;
+pub mod magic;
+pub mod monster;
+pub mod sprite;
+pub mod status;
+
+pub fn parse_monsters(rom_data: &[u8]) -> Result<Vec<monster::Monster>, Box<Error>> {
+    monster::parse(rom_data)
+}
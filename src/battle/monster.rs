@@ -0,0 +1,237 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::element::{self, Elements};
+use battle::status::{self, Status};
+use rom_map;
+use text;
+use utils::{check_len, get_u16, test_bit};
+
+const MONSTER_SIZE: usize = 0x20;
+const ITEMS_SIZE: usize = 4;
+const NAME_LEN: usize = 10;
+
+// Item index used for empty steal and drop slots.
+pub const NO_ITEM: u8 = 0xff;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Monster {
+    pub name: String,
+    pub level: u8,
+    pub hp: u16,
+    pub mp: u16,
+    pub xp: u16,
+    pub gp: u16,
+
+    pub speed: u8,
+    pub attack: u8,
+    pub hit_rate: u8,
+    pub evade: u8,
+    pub magic_evade: u8,
+    pub defense: u8,
+    pub magic_defense: u8,
+    pub magic_power: u8,
+
+    pub metamorph_pack: u8,
+    pub metamorph_rate: u8,
+
+    pub dies_at_zero_mp: bool,
+    pub unknown_flag_12_1: bool,
+    pub hide_name: bool,
+    pub unknown_flag_12_3: bool,
+    pub human: bool,
+    pub unknown_flag_12_5: bool,
+    pub critical_if_imp: bool,
+    pub undead: bool,
+    pub hard_to_run: bool,
+    pub first_strike: bool,
+    pub no_suplex: bool,
+    pub no_run: bool,
+    pub no_scan: bool,
+    pub no_sketch: bool,
+    pub special_event: bool,
+    pub no_control: bool,
+
+    pub status_immunities: Status,
+    pub absorb: Elements,
+    pub null: Elements,
+    pub weak: Elements,
+    pub attack_animation: u8,
+    pub initial_status: Status,
+
+    pub special_attack_name: String,
+    pub special_attack: u8,
+    pub special_attack_no_damage: bool,
+    pub special_attack_unblockable: bool,
+
+    pub rare_steal: u8,
+    pub common_steal: u8,
+    pub rare_drop: u8,
+    pub common_drop: u8,
+}
+
+fn table(rom_data: &[u8], snes_addr: usize, size: usize) -> Result<&[u8], Box<Error>> {
+    let addr = rom_map::snes_to_file(snes_addr);
+    let end = addr + size * rom_map::NUM_MONSTERS;
+    check_len(rom_data, addr, end - addr)?;
+    Ok(&rom_data[addr..end])
+}
+
+// `data` is the 0x20 byte stats record and `items` the 4 byte steal and drop
+// record.
+pub fn parse_monster(
+    data: &[u8],
+    items: &[u8],
+    name: &[u8],
+    special_name: &[u8],
+) -> Result<Monster, Box<Error>> {
+    if data.len() < MONSTER_SIZE || items.len() < ITEMS_SIZE {
+        return Err(SimpleError::new("monster data too short").into());
+    }
+
+    Ok(Monster {
+        name: text::decode_name(name),
+        level: data[0x10],
+        hp: get_u16(&data[0x08..]),
+        mp: get_u16(&data[0x0a..]),
+        xp: get_u16(&data[0x0c..]),
+        gp: get_u16(&data[0x0e..]),
+
+        speed: data[0x00],
+        attack: data[0x01],
+        hit_rate: data[0x02],
+        evade: data[0x03],
+        magic_evade: data[0x04],
+        defense: data[0x05],
+        magic_defense: data[0x06],
+        magic_power: data[0x07],
+
+        metamorph_pack: data[0x11] & 0x1f,
+        metamorph_rate: data[0x11] >> 5,
+
+        dies_at_zero_mp: test_bit(data[0x12], 0),
+        unknown_flag_12_1: test_bit(data[0x12], 1),
+        hide_name: test_bit(data[0x12], 2),
+        unknown_flag_12_3: test_bit(data[0x12], 3),
+        human: test_bit(data[0x12], 4),
+        unknown_flag_12_5: test_bit(data[0x12], 5),
+        critical_if_imp: test_bit(data[0x12], 6),
+        undead: test_bit(data[0x12], 7),
+        hard_to_run: test_bit(data[0x13], 0),
+        first_strike: test_bit(data[0x13], 1),
+        no_suplex: test_bit(data[0x13], 2),
+        no_run: test_bit(data[0x13], 3),
+        no_scan: test_bit(data[0x13], 4),
+        no_sketch: test_bit(data[0x13], 5),
+        special_event: test_bit(data[0x13], 6),
+        no_control: test_bit(data[0x13], 7),
+
+        status_immunities: status::decode(&data[0x14..0x17]),
+        absorb: element::decode(data[0x17]),
+        null: element::decode(data[0x18]),
+        weak: element::decode(data[0x19]),
+        attack_animation: data[0x1a],
+        initial_status: status::decode(&data[0x1b..0x1f]),
+
+        special_attack_name: text::decode_name(special_name),
+        special_attack: data[0x1f] & 0x3f,
+        special_attack_no_damage: test_bit(data[0x1f], 6),
+        special_attack_unblockable: test_bit(data[0x1f], 7),
+
+        rare_steal: items[0],
+        common_steal: items[1],
+        rare_drop: items[2],
+        common_drop: items[3],
+    })
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Monster>, Box<Error>> {
+    let data = table(rom_data, rom_map::MONSTER_DATA, MONSTER_SIZE)?;
+    let items = table(rom_data, rom_map::MONSTER_ITEMS, ITEMS_SIZE)?;
+    let names = table(rom_data, rom_map::MONSTER_NAMES, NAME_LEN)?;
+    let special_names = table(rom_data, rom_map::MONSTER_SPECIAL_NAMES, NAME_LEN)?;
+
+    let mut monsters = Vec::new();
+    for i in 0..rom_map::NUM_MONSTERS {
+        monsters.push(parse_monster(
+            &data[i * MONSTER_SIZE..],
+            &items[i * ITEMS_SIZE..],
+            &names[i * NAME_LEN..(i + 1) * NAME_LEN],
+            &special_names[i * NAME_LEN..(i + 1) * NAME_LEN],
+        )?);
+    }
+    Ok(monsters)
+}
+
+pub const CSV_HEADER: &str = "index,name,level,hp,mp,xp,gp,speed,attack,hit_rate,evade,\
+                              magic_evade,defense,magic_defense,magic_power,absorb,null,weak,\
+                              status_immunities,initial_status,special_attack_name,\
+                              special_attack,rare_steal,common_steal,rare_drop,common_drop";
+
+// One CSV row.  Element and status sets are space separated.
+pub fn csv_row(index: usize, m: &Monster) -> String {
+    format!(
+        "{},\"{}\",{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},\"{}\",{},{},{},{},{}",
+        index,
+        m.name,
+        m.level,
+        m.hp,
+        m.mp,
+        m.xp,
+        m.gp,
+        m.speed,
+        m.attack,
+        m.hit_rate,
+        m.evade,
+        m.magic_evade,
+        m.defense,
+        m.magic_defense,
+        m.magic_power,
+        m.absorb.names().join(" "),
+        m.null.names().join(" "),
+        m.weak.names().join(" "),
+        m.status_immunities.names().join(" "),
+        m.initial_status.names().join(" "),
+        m.special_attack_name,
+        m.special_attack,
+        m.rare_steal,
+        m.common_steal,
+        m.rare_drop,
+        m.common_drop
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        // Guard
+        let data = [
+            0x1e, 0x0b, 0x64, 0x00, 0x00, 0x64, 0x8c, 0x0a, 0x28, 0x00, 0x0f, 0x00, 0x30, 0x00,
+            0x2f, 0x00, 0x05, 0x41, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x46,
+        ];
+        let items = [0xff, 0xe8, 0xff, 0xe8];
+        let name = [0x86, 0xae, 0x9a, 0xab, 0x9d, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let m = parse_monster(&data, &items, &name, &name).unwrap();
+        assert_eq!("Guard", m.name);
+        assert_eq!(5, m.level);
+        assert_eq!(40, m.hp);
+        assert_eq!(48, m.xp);
+        assert_eq!(47, m.gp);
+        assert_eq!(1, m.metamorph_pack);
+        assert_eq!(2, m.metamorph_rate);
+        assert!(m.human);
+        assert!(m.weak.holy);
+        assert_eq!(6, m.special_attack);
+        assert!(m.special_attack_no_damage);
+        assert_eq!(NO_ITEM, m.rare_steal);
+        assert_eq!(
+            "0,\"Guard\",5,40,15,48,47,30,11,100,0,0,100,140,10,,,holy,,,\"Guard\",6,255,232,255,232",
+            csv_row(0, &m)
+        );
+        assert!(parse_monster(&data[..0x1f], &items, &name, &name).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use graphics::palette::{self, Kind};
+use graphics::tile::{self, Format, TILE_SIZE};
+use graphics::Image;
+use rom_map;
+use utils::check_len;
+
+const INFO_SIZE: usize = 5;
+const SMALL_MASK_SIZE: usize = 8;
+const LARGE_MASK_SIZE: usize = 32;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GraphicsInfo {
+    // In units of 8 bytes from the start of monster graphics.
+    pub offset: u16,
+    pub bpp3: bool,
+    // Large monsters use a 16x16 tile mask instead of 8x8.
+    pub large: bool,
+    pub unknown_bits: u8,
+    pub palette: u16,
+    pub mask: u8,
+}
+
+impl GraphicsInfo {
+    pub fn format(&self) -> Format {
+        if self.bpp3 {
+            Format::Bpp3
+        } else {
+            Format::Bpp4
+        }
+    }
+
+    // Width and height in tiles.
+    pub fn tiles(&self) -> usize {
+        if self.large {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+pub fn parse_info(data: &[u8]) -> Result<GraphicsInfo, Box<Error>> {
+    if data.len() < INFO_SIZE {
+        return Err(SimpleError::new("monster graphics info too short").into());
+    }
+    Ok(GraphicsInfo {
+        offset: (data[0] as u16 | (data[1] as u16) << 8) & 0x7fff,
+        bpp3: data[1] & 0x80 == 0x80,
+        large: data[2] & 0x80 == 0x80,
+        unknown_bits: (data[2] >> 2) & 0x1f,
+        palette: (data[2] as u16 & 0x3) << 8 | data[3] as u16,
+        mask: data[4],
+    })
+}
+
+pub fn info(rom_data: &[u8], monster: usize) -> Result<GraphicsInfo, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::MONSTER_GRAPHICS_INFO) + monster * INFO_SIZE;
+    check_len(rom_data, addr, INFO_SIZE)?;
+    parse_info(&rom_data[addr..])
+}
+
+// Mask rows are one bit per tile with the leftmost tile in the msb.  The
+// masks are found through two 16 bit pointers into their bank.
+fn mask(rom_data: &[u8], info: &GraphicsInfo) -> Result<Vec<bool>, Box<Error>> {
+    let (ptr, size) = if info.large {
+        (rom_map::MONSTER_LARGE_MASK_POINTER, LARGE_MASK_SIZE)
+    } else {
+        (rom_map::MONSTER_SMALL_MASK_POINTER, SMALL_MASK_SIZE)
+    };
+    let ptr = rom_map::snes_to_file(ptr);
+    check_len(rom_data, ptr, 2)?;
+    let addr = rom_map::snes_to_file(rom_map::MONSTER_MASK_BANK)
+        + (rom_data[ptr] as usize | (rom_data[ptr + 1] as usize) << 8)
+        + info.mask as usize * size;
+    check_len(rom_data, addr, size)?;
+    Ok(decode_mask(&rom_data[addr..addr + size]))
+}
+
+pub fn decode_mask(data: &[u8]) -> Vec<bool> {
+    let mut bits = Vec::new();
+    for b in data {
+        for i in 0..8 {
+            bits.push((b >> (7 - i)) & 0x1 == 0x1);
+        }
+    }
+    bits
+}
+
+pub fn render(rom_data: &[u8], monster: usize) -> Result<Image, Box<Error>> {
+    let info = info(rom_data, monster)?;
+    let mask = mask(rom_data, &info)?;
+
+    let format = info.format();
+    let num_tiles = mask.iter().filter(|m| **m).count();
+    let addr = rom_map::snes_to_file(rom_map::MONSTER_GRAPHICS) + info.offset as usize * 8;
+    check_len(rom_data, addr, num_tiles * format.tile_len())?;
+    let tiles = tile::decode(
+        &rom_data[addr..addr + num_tiles * format.tile_len()],
+        format,
+    );
+
+    // `info.palette` is in the same 8 color units as `Kind::Monster`.
+    let colors = 1 << format.bpp();
+    let palette = palette::read_span(
+        rom_data,
+        Kind::Monster,
+        info.palette as usize,
+        colors / Kind::Monster.colors(),
+    )?;
+
+    let size = info.tiles();
+    let mut image = Image::new(size * TILE_SIZE, size * TILE_SIZE);
+    let mut tiles = tiles.iter();
+    for (i, _) in mask.iter().enumerate().filter(|(_, m)| **m) {
+        if let Some(t) = tiles.next() {
+            image.draw_tile(
+                t,
+                &palette.colors,
+                (i % size) * TILE_SIZE,
+                (i / size) * TILE_SIZE,
+                false,
+                false,
+            );
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::Color;
+
+    #[test]
+    fn info_test() {
+        let info = parse_info(&[0x34, 0x92, 0x81, 0x05, 0x0a]).unwrap();
+        assert_eq!(0x1234, info.offset);
+        assert!(info.bpp3);
+        assert!(info.large);
+        assert_eq!(0x105, info.palette);
+        assert_eq!(10, info.mask);
+        assert_eq!(16, info.tiles());
+
+        let mask = decode_mask(&[0x81, 0x00]);
+        assert!(mask[0] && mask[7]);
+        assert_eq!(2, mask.iter().filter(|m| **m).count());
+    }
+
+    #[test]
+    fn render_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let mut put = |snes_addr: usize, data: &[u8]| {
+            let addr = rom_map::snes_to_file(snes_addr);
+            rom[addr..addr + data.len()].copy_from_slice(data);
+        };
+        let solid = |c: u8| [c; TILE_SIZE * TILE_SIZE];
+        let mut corner = [0u8; TILE_SIZE * TILE_SIZE];
+        corner[0] = 2;
+
+        // Monster 0 is small and 3bpp with mask 1 of the small masks at
+        // 0x1000 in the mask bank.  Tiles 0, 7, and 9 are set.
+        put(
+            rom_map::MONSTER_GRAPHICS_INFO,
+            &[0x02, 0x80, 0x00, 0x01, 0x01],
+        );
+        put(rom_map::MONSTER_SMALL_MASK_POINTER, &[0x00, 0x10]);
+        put(
+            rom_map::MONSTER_MASK_BANK + 0x1000 + SMALL_MASK_SIZE,
+            &[0x81, 0x40],
+        );
+        put(
+            rom_map::MONSTER_GRAPHICS + 0x02 * 8,
+            &tile::encode(&[solid(1), solid(7), corner], Format::Bpp3),
+        );
+
+        // Monster 1 is large and 4bpp with mask 0 of the large masks at
+        // 0x2000 in the mask bank.  Only tile 17 is set.
+        put(
+            rom_map::MONSTER_GRAPHICS_INFO + INFO_SIZE,
+            &[0x10, 0x00, 0x80, 0x02, 0x00],
+        );
+        put(rom_map::MONSTER_LARGE_MASK_POINTER, &[0x00, 0x20]);
+        put(rom_map::MONSTER_MASK_BANK + 0x2000 + 2, &[0x40]);
+        put(
+            rom_map::MONSTER_GRAPHICS + 0x10 * 8,
+            &tile::encode(&[solid(15)], Format::Bpp4),
+        );
+
+        // Palette 1 holds red, green, and blue as colors 1, 2, and 7.  A 4bpp
+        // palette at 2 spans into palette 3 for its upper 8 colors.
+        let palette_addr = |p: usize, c: usize| rom_map::MONSTER_PALETTES + (p * 8 + c) * 2;
+        put(palette_addr(1, 1), &[0x1f, 0x00, 0xe0, 0x03]);
+        put(palette_addr(1, 7), &[0x00, 0x7c]);
+        put(palette_addr(3, 7), &[0xff, 0x7f]);
+
+        let red = palette::decode_color(&[0x1f, 0x00]);
+        let green = palette::decode_color(&[0xe0, 0x03]);
+        let blue = palette::decode_color(&[0x00, 0x7c]);
+        let white = palette::decode_color(&[0xff, 0x7f]);
+
+        let image = render(&rom, 0).unwrap();
+        assert_eq!(8 * TILE_SIZE, image.width);
+        assert_eq!(red, image.get_pixel(0, 0));
+        assert_eq!(Color::transparent(), image.get_pixel(8, 0));
+        assert_eq!(blue, image.get_pixel(63, 7));
+        assert_eq!(green, image.get_pixel(8, 8));
+        assert_eq!(Color::transparent(), image.get_pixel(9, 8));
+
+        let image = render(&rom, 1).unwrap();
+        assert_eq!(16 * TILE_SIZE, image.width);
+        assert_eq!(white, image.get_pixel(8, 8));
+        assert_eq!(white, image.get_pixel(15, 15));
+        assert_eq!(Color::transparent(), image.get_pixel(16, 8));
+        assert_eq!(Color::transparent(), image.get_pixel(7, 8));
+    }
+}
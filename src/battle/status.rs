@@ -0,0 +1,187 @@
+use utils::test_bit;
+
+// The four status bytes as used by monsters, items, and spells.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Status {
+    // Status 1
+    pub dark: bool,
+    pub zombie: bool,
+    pub poison: bool,
+    pub magitek: bool,
+    pub vanish: bool,
+    pub imp: bool,
+    pub petrify: bool,
+    pub death: bool,
+    // Status 2
+    pub condemned: bool,
+    pub near_fatal: bool,
+    pub image: bool,
+    pub mute: bool,
+    pub berserk: bool,
+    pub muddle: bool,
+    pub seizure: bool,
+    pub sleep: bool,
+    // Status 3
+    pub dance: bool,
+    pub regen: bool,
+    pub slow: bool,
+    pub haste: bool,
+    pub stop: bool,
+    pub shell: bool,
+    pub safe: bool,
+    pub reflect: bool,
+    // Status 4
+    pub rage: bool,
+    pub frozen: bool,
+    pub life_3: bool,
+    pub morph: bool,
+    pub chant: bool,
+    pub hide: bool,
+    pub interceptor: bool,
+    pub float: bool,
+}
+
+pub const NAMES: [&str; 32] = [
+    "dark",
+    "zombie",
+    "poison",
+    "magitek",
+    "vanish",
+    "imp",
+    "petrify",
+    "death",
+    "condemned",
+    "near_fatal",
+    "image",
+    "mute",
+    "berserk",
+    "muddle",
+    "seizure",
+    "sleep",
+    "dance",
+    "regen",
+    "slow",
+    "haste",
+    "stop",
+    "shell",
+    "safe",
+    "reflect",
+    "rage",
+    "frozen",
+    "life_3",
+    "morph",
+    "chant",
+    "hide",
+    "interceptor",
+    "float",
+];
+
+impl Status {
+    fn flags(&self) -> [bool; 32] {
+        [
+            self.dark,
+            self.zombie,
+            self.poison,
+            self.magitek,
+            self.vanish,
+            self.imp,
+            self.petrify,
+            self.death,
+            self.condemned,
+            self.near_fatal,
+            self.image,
+            self.mute,
+            self.berserk,
+            self.muddle,
+            self.seizure,
+            self.sleep,
+            self.dance,
+            self.regen,
+            self.slow,
+            self.haste,
+            self.stop,
+            self.shell,
+            self.safe,
+            self.reflect,
+            self.rage,
+            self.frozen,
+            self.life_3,
+            self.morph,
+            self.chant,
+            self.hide,
+            self.interceptor,
+            self.float,
+        ]
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.flags()
+            .iter()
+            .zip(NAMES.iter())
+            .filter(|(set, _)| **set)
+            .map(|(_, n)| *n)
+            .collect()
+    }
+}
+
+// Decodes up to four status bytes.  Missing bytes are treated as 0.
+pub fn decode(data: &[u8]) -> Status {
+    let b = |i: usize, bit: u8| data.get(i).map(|b| test_bit(*b, bit)).unwrap_or(false);
+    Status {
+        dark: b(0, 0),
+        zombie: b(0, 1),
+        poison: b(0, 2),
+        magitek: b(0, 3),
+        vanish: b(0, 4),
+        imp: b(0, 5),
+        petrify: b(0, 6),
+        death: b(0, 7),
+        condemned: b(1, 0),
+        near_fatal: b(1, 1),
+        image: b(1, 2),
+        mute: b(1, 3),
+        berserk: b(1, 4),
+        muddle: b(1, 5),
+        seizure: b(1, 6),
+        sleep: b(1, 7),
+        dance: b(2, 0),
+        regen: b(2, 1),
+        slow: b(2, 2),
+        haste: b(2, 3),
+        stop: b(2, 4),
+        shell: b(2, 5),
+        safe: b(2, 6),
+        reflect: b(2, 7),
+        rage: b(3, 0),
+        frozen: b(3, 1),
+        life_3: b(3, 2),
+        morph: b(3, 3),
+        chant: b(3, 4),
+        hide: b(3, 5),
+        interceptor: b(3, 6),
+        float: b(3, 7),
+    }
+}
+
+pub fn encode(status: &Status) -> [u8; 4] {
+    let mut data = [0u8; 4];
+    for (i, set) in status.flags().iter().enumerate() {
+        if *set {
+            data[i / 8] |= 1 << (i % 8);
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let s = decode(&[0x81, 0x00, 0x40]);
+        assert!(s.dark && s.death && s.safe);
+        assert_eq!(vec!["dark", "death", "safe"], s.names());
+        assert_eq!([0x81, 0x00, 0x40, 0x00], encode(&s));
+    }
+}
@@ -0,0 +1,280 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::esper::Bonus;
+use rom_map;
+use text;
+use utils::check_len;
+
+const CHARACTER_SIZE: usize = 0x16;
+const NAME_SIZE: usize = 6;
+const NUM_LEVEL_UPS: usize = 98;
+
+pub const MAX_LEVEL: u8 = 99;
+const MAX_HP: u16 = 9999;
+const MAX_MP: u16 = 999;
+const MAX_STAT: u8 = 128;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Character {
+    pub name: String,
+    // Level 1 HP and MP.
+    pub hp: u8,
+    pub mp: u8,
+    pub commands: [u8; 4],
+
+    pub vigor: u8,
+    pub speed: u8,
+    pub stamina: u8,
+    pub magic_power: u8,
+    pub battle_power: u8,
+    pub defense: u8,
+    pub magic_defense: u8,
+    pub evade: u8,
+    pub magic_evade: u8,
+
+    pub right_hand: u8,
+    pub left_hand: u8,
+    pub body: u8,
+    pub helmet: u8,
+    pub relics: [u8; 2],
+
+    // Controls the level a character joins at relative to the party.
+    pub level_flags: u8,
+}
+
+// Level up tables shared by all characters.  Entry `i` is for reaching level
+// `i + 2`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Growth {
+    pub hp: Vec<u8>,
+    pub mp: Vec<u8>,
+    // Stored divided by 8.
+    pub experience: Vec<u16>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Stats {
+    pub level: u8,
+    pub hp: u16,
+    pub mp: u16,
+    pub vigor: u8,
+    pub speed: u8,
+    pub stamina: u8,
+    pub magic_power: u8,
+}
+
+pub fn parse_character(data: &[u8], name: &[u8]) -> Result<Character, Box<Error>> {
+    if data.len() < CHARACTER_SIZE {
+        return Err(SimpleError::new("character data too short").into());
+    }
+
+    Ok(Character {
+        name: text::decode_name(name),
+        hp: data[0x00],
+        mp: data[0x01],
+        commands: [data[0x02], data[0x03], data[0x04], data[0x05]],
+
+        vigor: data[0x06],
+        speed: data[0x07],
+        stamina: data[0x08],
+        magic_power: data[0x09],
+        battle_power: data[0x0a],
+        defense: data[0x0b],
+        magic_defense: data[0x0c],
+        evade: data[0x0d],
+        magic_evade: data[0x0e],
+
+        right_hand: data[0x0f],
+        left_hand: data[0x10],
+        body: data[0x11],
+        helmet: data[0x12],
+        relics: [data[0x13], data[0x14]],
+
+        level_flags: data[0x15],
+    })
+}
+
+pub fn encode_character(c: &Character) -> Vec<u8> {
+    let mut data = vec![c.hp, c.mp];
+    data.extend_from_slice(&c.commands);
+    data.extend_from_slice(&[
+        c.vigor,
+        c.speed,
+        c.stamina,
+        c.magic_power,
+        c.battle_power,
+        c.defense,
+        c.magic_defense,
+        c.evade,
+        c.magic_evade,
+        c.right_hand,
+        c.left_hand,
+        c.body,
+        c.helmet,
+        c.relics[0],
+        c.relics[1],
+        c.level_flags,
+    ]);
+    data
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Character>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::CHARACTER_DATA);
+    let names = rom_map::snes_to_file(rom_map::CHARACTER_NAMES);
+    check_len(rom_data, addr, rom_map::NUM_CHARACTERS * CHARACTER_SIZE)?;
+    check_len(rom_data, names, rom_map::NUM_CHARACTERS * NAME_SIZE)?;
+
+    let mut characters = Vec::new();
+    for i in 0..rom_map::NUM_CHARACTERS {
+        characters.push(parse_character(
+            &rom_data[addr + i * CHARACTER_SIZE..],
+            &rom_data[names + i * NAME_SIZE..names + (i + 1) * NAME_SIZE],
+        )?);
+    }
+    Ok(characters)
+}
+
+pub fn write(rom_data: &mut [u8], characters: &[Character]) -> Result<(), Box<Error>> {
+    if characters.len() != rom_map::NUM_CHARACTERS {
+        return Err(SimpleError::new(format!(
+            "expected {} characters.  Got {}.",
+            rom_map::NUM_CHARACTERS,
+            characters.len()
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(rom_map::CHARACTER_DATA);
+    let names = rom_map::snes_to_file(rom_map::CHARACTER_NAMES);
+    check_len(rom_data, addr, rom_map::NUM_CHARACTERS * CHARACTER_SIZE)?;
+    check_len(rom_data, names, rom_map::NUM_CHARACTERS * NAME_SIZE)?;
+
+    for (i, c) in characters.iter().enumerate() {
+        let start = addr + i * CHARACTER_SIZE;
+        rom_data[start..start + CHARACTER_SIZE].copy_from_slice(&encode_character(c));
+        let start = names + i * NAME_SIZE;
+        rom_data[start..start + NAME_SIZE].copy_from_slice(&text::encode_name(&c.name, NAME_SIZE)?);
+    }
+    Ok(())
+}
+
+pub fn parse_growth(rom_data: &[u8]) -> Result<Growth, Box<Error>> {
+    let hp = rom_map::snes_to_file(rom_map::LEVEL_UP_HP);
+    let mp = rom_map::snes_to_file(rom_map::LEVEL_UP_MP);
+    let xp = rom_map::snes_to_file(rom_map::EXPERIENCE_TABLE);
+    check_len(rom_data, hp, NUM_LEVEL_UPS)?;
+    check_len(rom_data, mp, NUM_LEVEL_UPS)?;
+    check_len(rom_data, xp, NUM_LEVEL_UPS * 2)?;
+
+    Ok(Growth {
+        hp: rom_data[hp..hp + NUM_LEVEL_UPS].to_vec(),
+        mp: rom_data[mp..mp + NUM_LEVEL_UPS].to_vec(),
+        experience: rom_data[xp..xp + NUM_LEVEL_UPS * 2]
+            .chunks(2)
+            .map(|c| c[0] as u16 | (c[1] as u16) << 8)
+            .collect(),
+    })
+}
+
+pub fn write_growth(rom_data: &mut [u8], growth: &Growth) -> Result<(), Box<Error>> {
+    if growth.hp.len() != NUM_LEVEL_UPS
+        || growth.mp.len() != NUM_LEVEL_UPS
+        || growth.experience.len() != NUM_LEVEL_UPS
+    {
+        return Err(
+            SimpleError::new(format!("level up tables need {} entries", NUM_LEVEL_UPS)).into(),
+        );
+    }
+    let hp = rom_map::snes_to_file(rom_map::LEVEL_UP_HP);
+    let mp = rom_map::snes_to_file(rom_map::LEVEL_UP_MP);
+    let xp = rom_map::snes_to_file(rom_map::EXPERIENCE_TABLE);
+    check_len(rom_data, hp, NUM_LEVEL_UPS)?;
+    check_len(rom_data, mp, NUM_LEVEL_UPS)?;
+    check_len(rom_data, xp, NUM_LEVEL_UPS * 2)?;
+
+    rom_data[hp..hp + NUM_LEVEL_UPS].copy_from_slice(&growth.hp);
+    rom_data[mp..mp + NUM_LEVEL_UPS].copy_from_slice(&growth.mp);
+    for (i, e) in growth.experience.iter().enumerate() {
+        rom_data[xp + i * 2] = *e as u8;
+        rom_data[xp + i * 2 + 1] = (e >> 8) as u8;
+    }
+    Ok(())
+}
+
+impl Growth {
+    // Total experience needed to reach `level`.
+    pub fn experience_for(&self, level: u8) -> u32 {
+        self.experience
+            .iter()
+            .take(level.saturating_sub(1) as usize)
+            .map(|e| *e as u32 * 8)
+            .sum()
+    }
+}
+
+// Projects a character's stats at `level` assuming `bonus` is applied at
+// every level up from level 1.
+pub fn project(c: &Character, growth: &Growth, level: u8, bonus: Option<Bonus>) -> Stats {
+    let mut stats = Stats {
+        level: 1,
+        hp: c.hp as u16,
+        mp: c.mp as u16,
+        vigor: c.vigor,
+        speed: c.speed,
+        stamina: c.stamina,
+        magic_power: c.magic_power,
+    };
+    let stat = |s: u8, n: u8| s.saturating_add(n).min(MAX_STAT);
+    let percent = |gain: u8, p: u8| gain as u16 + gain as u16 * p as u16 / 100;
+
+    while stats.level < level.min(MAX_LEVEL) {
+        let i = stats.level as usize - 1;
+        let mut hp = growth.hp.get(i).cloned().unwrap_or(0) as u16;
+        let mut mp = growth.mp.get(i).cloned().unwrap_or(0) as u16;
+        match bonus {
+            Some(Bonus::Hp(p)) => hp = percent(hp as u8, p),
+            Some(Bonus::Mp(p)) => mp = percent(mp as u8, p),
+            Some(Bonus::Vigor(n)) => stats.vigor = stat(stats.vigor, n),
+            Some(Bonus::Speed(n)) => stats.speed = stat(stats.speed, n),
+            Some(Bonus::Stamina(n)) => stats.stamina = stat(stats.stamina, n),
+            Some(Bonus::MagicPower(n)) => stats.magic_power = stat(stats.magic_power, n),
+            None => (),
+        }
+        stats.hp = (stats.hp + hp).min(MAX_HP);
+        stats.mp = (stats.mp + mp).min(MAX_MP);
+        stats.level += 1;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_test() {
+        let data = [
+            0x28, 0x10, 0x00, 0x02, 0x0a, 0x01, 0x1f, 0x21, 0x1c, 0x27, 0x0c, 0x2a, 0x21, 0x05,
+            0x07, 0x55, 0xff, 0x9c, 0xff, 0xff, 0xff, 0x00,
+        ];
+        let c = parse_character(&data, &[0x93, 0x9e, 0xab, 0xab, 0x9a, 0xff]).unwrap();
+        assert_eq!("Terra", c.name);
+        assert_eq!(40, c.hp);
+        assert_eq!(31, c.vigor);
+        assert_eq!(data.to_vec(), encode_character(&c));
+
+        let growth = Growth {
+            hp: vec![10; NUM_LEVEL_UPS],
+            mp: vec![4; NUM_LEVEL_UPS],
+            experience: vec![4; NUM_LEVEL_UPS],
+        };
+        assert_eq!(64, growth.experience_for(3));
+
+        let s = project(&c, &growth, 5, None);
+        assert_eq!((5, 80, 32), (s.level, s.hp, s.mp));
+        let s = project(&c, &growth, 5, Some(Bonus::Hp(50)));
+        assert_eq!(100, s.hp);
+        let s = project(&c, &growth, 99, Some(Bonus::Vigor(2)));
+        assert_eq!(MAX_STAT, s.vigor);
+    }
+}
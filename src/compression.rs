@@ -0,0 +1,333 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use utils::check_len;
+
+// FF6 uses an LZSS variant with a 2KB ring buffer.  Compressed data starts
+// with a 16 bit length (including the length itself) followed by groups of
+// a flag byte and eight items.  A set flag bit means the item is a literal
+// byte.  A clear bit means the item is a two byte back reference: 11 bits
+// of ring buffer position followed by 5 bits of length - 3.
+const BUFFER_SIZE: usize = 0x800;
+const BUFFER_MASK: usize = BUFFER_SIZE - 1;
+const BUFFER_START: usize = 0x7de;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x1f + MIN_MATCH;
+const MAX_COMPRESSED_LEN: usize = 0xffff;
+
+// Cost, in bits, of each item type including its flag bit.
+const LITERAL_COST: usize = 9;
+const MATCH_COST: usize = 17;
+
+// Hash chain search depth for Mode::Fast.
+const FAST_CHAIN_LIMIT: usize = 32;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+    // Greedy longest match with a bounded search.
+    Fast,
+    // Exhaustive search and a shortest path parse over every match length.
+    Optimal,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Item {
+    Literal(u8),
+    Match { pos: usize, len: usize },
+}
+
+// Returns the length of the compressed block at the start of data.
+pub fn compressed_len(data: &[u8]) -> Result<usize, Box<Error>> {
+    if data.len() < 2 {
+        return Err(SimpleError::new("data does not contain at least 0x2 bytes").into());
+    }
+    Ok(data[0] as usize | (data[1] as usize) << 8)
+}
+
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+    let len = compressed_len(data)?;
+    check_len(data, 0, len)?;
+
+    let mut buffer = [0u8; BUFFER_SIZE];
+    let mut buffer_pos = BUFFER_START;
+    let mut out = Vec::new();
+    let mut i = 2;
+
+    while i < len {
+        let flags = data[i];
+        i += 1;
+        for bit in 0..8 {
+            if i >= len {
+                break;
+            }
+            if (flags >> bit) & 0x1 == 0x1 {
+                out.push(data[i]);
+                buffer[buffer_pos] = data[i];
+                buffer_pos = (buffer_pos + 1) & BUFFER_MASK;
+                i += 1;
+            } else {
+                if i + 1 >= len {
+                    return Err(
+                        SimpleError::new(format!("truncated back reference at 0x{:x}", i)).into(),
+                    );
+                }
+                let src = data[i] as usize | ((data[i + 1] as usize & 0x7) << 8);
+                let match_len = (data[i + 1] as usize >> 3) + MIN_MATCH;
+                for j in 0..match_len {
+                    let b = buffer[(src + j) & BUFFER_MASK];
+                    out.push(b);
+                    buffer[buffer_pos] = b;
+                    buffer_pos = (buffer_pos + 1) & BUFFER_MASK;
+                }
+                i += 2;
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+// The decompressor's ring buffer starts out zeroed, so matches may reference
+// up to a full buffer of zeros "before" the data.  Searching is done over
+// the data with that prefix prepended.
+struct Matcher {
+    buf: Vec<u8>,
+    head: Vec<usize>,
+    prev: Vec<usize>,
+    chain_limit: Option<usize>,
+}
+
+const NO_POS: usize = usize::MAX;
+
+fn hash(data: &[u8]) -> usize {
+    ((data[0] as usize) << 8 ^ (data[1] as usize) << 4 ^ data[2] as usize) & 0xffff
+}
+
+impl Matcher {
+    fn new(data: &[u8], chain_limit: Option<usize>) -> Matcher {
+        let mut buf = vec![0u8; BUFFER_SIZE];
+        buf.extend_from_slice(data);
+        let mut m = Matcher {
+            head: vec![NO_POS; 0x10000],
+            prev: vec![NO_POS; buf.len()],
+            buf: buf,
+            chain_limit: chain_limit,
+        };
+        // Only the last BUFFER_SIZE - 1 bytes of the zero prefix are within
+        // reach of the first data byte.
+        for i in 1..BUFFER_SIZE {
+            m.insert(i);
+        }
+        m
+    }
+
+    fn insert(&mut self, i: usize) {
+        if i + MIN_MATCH > self.buf.len() {
+            return;
+        }
+        let h = hash(&self.buf[i..]);
+        self.prev[i] = self.head[h];
+        self.head[h] = i;
+    }
+
+    // Finds the longest match for data position `pos`.  All positions before
+    // pos must have been inserted.
+    fn find(&self, pos: usize) -> Option<(usize, usize)> {
+        let i = pos + BUFFER_SIZE;
+        let max_len = MAX_MATCH.min(self.buf.len() - i);
+        if max_len < MIN_MATCH {
+            return None;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[hash(&self.buf[i..])];
+        let mut searched = 0;
+        while candidate != NO_POS && candidate + BUFFER_SIZE > i {
+            if let Some(limit) = self.chain_limit {
+                if searched >= limit {
+                    break;
+                }
+            }
+            searched += 1;
+
+            // Matches may overlap the data being produced.
+            let mut len = 0;
+            while len < max_len && self.buf[candidate + len] == self.buf[i + len] {
+                len += 1;
+            }
+            if len >= MIN_MATCH && best.is_none_or(|(_, l)| len > l) {
+                best = Some((candidate, len));
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+        }
+
+        best.map(|(candidate, len)| {
+            // Convert from search buffer index to ring buffer position.
+            let src = candidate as isize - BUFFER_SIZE as isize;
+            let ring = (BUFFER_START as isize + src) as usize & BUFFER_MASK;
+            (ring, len)
+        })
+    }
+}
+
+fn parse_fast(data: &[u8]) -> Vec<Item> {
+    let mut m = Matcher::new(data, Some(FAST_CHAIN_LIMIT));
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        match m.find(pos) {
+            Some((ring, len)) => {
+                items.push(Item::Match {
+                    pos: ring,
+                    len: len,
+                });
+                for i in pos..pos + len {
+                    m.insert(i + BUFFER_SIZE);
+                }
+                pos += len;
+            }
+            None => {
+                items.push(Item::Literal(data[pos]));
+                m.insert(pos + BUFFER_SIZE);
+                pos += 1;
+            }
+        }
+    }
+    items
+}
+
+fn parse_optimal(data: &[u8]) -> Vec<Item> {
+    let mut m = Matcher::new(data, None);
+    let mut matches = Vec::with_capacity(data.len());
+    for pos in 0..data.len() {
+        matches.push(m.find(pos));
+        m.insert(pos + BUFFER_SIZE);
+    }
+
+    // cost[i] is the cheapest encoding of data[i..].  Any prefix of a match
+    // is also a match so every length up to the longest is considered.
+    let n = data.len();
+    let mut cost = vec![0usize; n + 1];
+    let mut choice = vec![0usize; n + 1];
+    for i in (0..n).rev() {
+        cost[i] = LITERAL_COST + cost[i + 1];
+        choice[i] = 1;
+        if let Some((_, len)) = matches[i] {
+            for l in MIN_MATCH..len + 1 {
+                let c = MATCH_COST + cost[i + l];
+                if c <= cost[i] {
+                    cost[i] = c;
+                    choice[i] = l;
+                }
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while pos < n {
+        let len = choice[pos];
+        if len == 1 {
+            items.push(Item::Literal(data[pos]));
+        } else {
+            let (ring, _) = matches[pos].unwrap();
+            items.push(Item::Match {
+                pos: ring,
+                len: len,
+            });
+        }
+        pos += len;
+    }
+    items
+}
+
+fn encode(items: &[Item]) -> Result<Vec<u8>, Box<Error>> {
+    let mut out = vec![0u8, 0u8];
+    for group in items.chunks(8) {
+        let flag_index = out.len();
+        out.push(0);
+        for (bit, item) in group.iter().enumerate() {
+            match *item {
+                Item::Literal(b) => {
+                    out[flag_index] |= 1 << bit;
+                    out.push(b);
+                }
+                Item::Match { pos, len } => {
+                    out.push((pos & 0xff) as u8);
+                    out.push(((pos >> 8) & 0x7) as u8 | ((len - MIN_MATCH) << 3) as u8);
+                }
+            }
+        }
+    }
+
+    if out.len() > MAX_COMPRESSED_LEN {
+        return Err(SimpleError::new(format!(
+            "compressed data is 0x{:x} bytes, larger than 0x{:x}",
+            out.len(),
+            MAX_COMPRESSED_LEN
+        ))
+        .into());
+    }
+    let len = out.len();
+    out[0] = (len & 0xff) as u8;
+    out[1] = (len >> 8) as u8;
+    Ok(out)
+}
+
+pub fn compress(data: &[u8], mode: Mode) -> Result<Vec<u8>, Box<Error>> {
+    let items = match mode {
+        Mode::Fast => parse_fast(data),
+        Mode::Optimal => parse_optimal(data),
+    };
+    encode(&items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..0x1800 {
+            data.push(match i % 0x300 {
+                0..=0x3f => 0,
+                0x40..=0xbf => (i % 7) as u8,
+                _ => ((i * 0x1d) >> 3) as u8,
+            });
+        }
+        data
+    }
+
+    #[test]
+    fn decompress_test() {
+        // "ABC" as literals followed by a back reference to it of length 6
+        // and a reference into the zeroed portion of the ring buffer.
+        let data = [0x0a, 0x00, 0x07, 0x41, 0x42, 0x43, 0xde, 0x1f, 0x00, 0x00];
+        assert_eq!(
+            vec![0x41, 0x42, 0x43, 0x41, 0x42, 0x43, 0x41, 0x42, 0x43, 0x00, 0x00, 0x00],
+            decompress(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let data = test_data();
+        for mode in [Mode::Fast, Mode::Optimal].iter() {
+            let compressed = compress(&data, *mode).unwrap();
+            assert_eq!(compressed.len(), compressed_len(&compressed).unwrap());
+            assert_eq!(data, decompress(&compressed).unwrap());
+        }
+        assert_eq!(vec![0x02, 0x00], compress(&[], Mode::Optimal).unwrap());
+    }
+
+    #[test]
+    fn optimal_not_larger_test() {
+        let data = test_data();
+        let fast = compress(&data, Mode::Fast).unwrap();
+        let optimal = compress(&data, Mode::Optimal).unwrap();
+        assert!(optimal.len() <= fast.len());
+    }
+}
@@ -9,6 +9,7 @@ enum Tag {
     DialogWait = 0x49,
     DispTextBoxWait = 0x4B,
 
+    InvokeEventBattle = 0x4d,
     InvokeBattle = 0x4e,
 
     InvokeBattleOnChestOpen = 0x8e,
@@ -59,6 +60,12 @@ pub enum Event {
     DialogWait,
     InvokeBattle,
     InvokeBattleOnChestOpen,
+    // Starts a battle from a formation pack rather than the location's
+    // random encounters.
+    InvokeEventBattle {
+        pack: u8,
+        background: u8,
+    },
     JumpIfBattleSwitch {
         switch: u8,
         addr: u32,
@@ -126,6 +133,16 @@ named!(parse_disp_text_box_wait<&[u8], Event>,
             wait: true,
             })));
 
+named!(parse_invoke_event_battle<&[u8], Event>,
+    do_parse!(
+        tag!(&[Tag::InvokeEventBattle as u8]) >>
+        pack: le_u8 >>
+        background: le_u8 >>
+        (Event::InvokeEventBattle{
+            pack: pack,
+            background: background,
+            })));
+
 named!(parse_jump_if_battle_switch<&[u8], Event>,
     do_parse!(
         tag!(&[Tag::JumpIfBattleSwitch as u8]) >>
@@ -153,6 +170,7 @@ named!(pub parse_event<&[u8], Event>, alt!(
     parse_branch_if_event_bit |
     parse_call |
     parse_disp_text_box_wait |
+    parse_invoke_event_battle |
     parse_jump_if_battle_switch |
 
     // This is kinda a hack to force an error on unrecognized tags.  We know
@@ -215,6 +233,15 @@ mod tests {
                 .unwrap()
                 .1
         );
+        assert_eq!(
+            Event::InvokeEventBattle {
+                pack: 0x44,
+                background: 0x80
+            },
+            parse_event(&[Tag::InvokeEventBattle as u8, 0x44, 0x80])
+                .unwrap()
+                .1
+        );
 
         assert_eq!(
             Event::JumpIfBattleSwitch {
@@ -249,6 +276,6 @@ mod tests {
                 }]
             },
             parse_script(&[0x4b, 0x85, 0x0b, 0xfe]).unwrap().1
-        )
+        );
     }
 }
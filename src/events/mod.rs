@@ -1,4 +1,5 @@
 use nom::{error_to_list, ErrorKind};
+use simple_error::SimpleError;
 use std::collections::HashMap;
 use std::error;
 use std::error::Error;
@@ -63,6 +64,15 @@ fn err_text(data: &[u8], e: &nom::Err<&[u8]>) -> String {
     }
 }
 
+// Parses a single world character script from the start of data and returns
+// the raw map word, x, and y of each map it loads.
+pub fn world_char_map_loads(data: &[u8]) -> Result<Vec<(u16, u8, u8)>, Box<Error>> {
+    match world_char::parse_script(data) {
+        Ok((_, script)) => Ok(script.map_loads()),
+        Err(_) => Err(SimpleError::new("not a world character script").into()),
+    }
+}
+
 pub fn parse(data: &[u8]) -> Result<HashMap<usize, Script>, Box<Error>> {
     let mut d = data;
     let mut scripts = HashMap::new();
@@ -26,7 +26,7 @@ macro_rules! parse_range {
             res = Err(nom::Err::Error(nom::Context::Code($i, ErrorKind::Tag)))
         }
         res
-    };};
+    }};
 }
 
 #[cfg(test)]
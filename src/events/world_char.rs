@@ -164,6 +164,19 @@ pub struct Script {
     events: Vec<Event>,
 }
 
+impl Script {
+    // Raw map word, x, and y of every LoadMap event.
+    pub fn map_loads(&self) -> Vec<(u16, u8, u8)> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                Event::LoadMap { map, x, y, .. } => Some((*map, *x, *y)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 named!(parse_clear_set_event_bit<&[u8], Event>,
     do_parse!(
         op: parse_range!(Tag::SetEventBit, Tag::ClearEventBit) >>
@@ -0,0 +1,327 @@
+use std::error::Error;
+
+use field::exit::Destination;
+use field::sprite;
+use field::Location;
+use graphics::palette::{self, Kind};
+use graphics::tile::TILE_SIZE as SPRITE_TILE_SIZE;
+use graphics::{Color, Image};
+
+const TILE_SIZE: usize = 16;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MarkerKind {
+    Npc,
+    Trigger,
+    Exit,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Marker {
+    pub kind: MarkerKind,
+    pub index: usize,
+    // Position and size in map tiles.
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    pub direction: Option<u8>,
+    pub event_addr: u32,
+    pub dest: Option<Destination>,
+}
+
+impl Marker {
+    pub fn label(&self) -> String {
+        let prefix = match self.kind {
+            MarkerKind::Npc => "N",
+            MarkerKind::Trigger => "T",
+            MarkerKind::Exit => "X",
+        };
+        format!("{}{:X}", prefix, self.index)
+    }
+
+    fn color(&self) -> Color {
+        match self.kind {
+            MarkerKind::Npc => Color {
+                r: 0xff,
+                g: 0x40,
+                b: 0x40,
+                a: 0xff,
+            },
+            MarkerKind::Trigger => Color {
+                r: 0xff,
+                g: 0xff,
+                b: 0x40,
+                a: 0xff,
+            },
+            MarkerKind::Exit => Color {
+                r: 0x40,
+                g: 0xc0,
+                b: 0xff,
+                a: 0xff,
+            },
+        }
+    }
+}
+
+pub fn markers(location: &Location) -> Vec<Marker> {
+    let mut markers = Vec::new();
+    for (i, npc) in location.npcs.iter().enumerate() {
+        markers.push(Marker {
+            kind: MarkerKind::Npc,
+            index: i,
+            x: npc.x as usize,
+            y: npc.y as usize,
+            w: 1,
+            h: 1,
+            direction: Some(npc.start_direction),
+            event_addr: npc.event_addr,
+            dest: None,
+        });
+    }
+    for (i, trigger) in location.triggers.iter().enumerate() {
+        markers.push(Marker {
+            kind: MarkerKind::Trigger,
+            index: i,
+            x: trigger.x as usize,
+            y: trigger.y as usize,
+            w: 1,
+            h: 1,
+            direction: None,
+            event_addr: trigger.event_addr,
+            dest: None,
+        });
+    }
+    // Short exits are numbered first, followed by the long exits.
+    for (i, exit) in location.short_exits.iter().enumerate() {
+        markers.push(Marker {
+            kind: MarkerKind::Exit,
+            index: i,
+            x: exit.x as usize,
+            y: exit.y as usize,
+            w: 1,
+            h: 1,
+            direction: None,
+            event_addr: 0,
+            dest: Some(exit.dest.clone()),
+        });
+    }
+    for (i, exit) in location.long_exits.iter().enumerate() {
+        let len = exit.length as usize + 1;
+        markers.push(Marker {
+            kind: MarkerKind::Exit,
+            index: location.short_exits.len() + i,
+            x: exit.x as usize,
+            y: exit.y as usize,
+            w: if exit.vertical { 1 } else { len },
+            h: if exit.vertical { len } else { 1 },
+            direction: None,
+            event_addr: 0,
+            dest: Some(exit.dest.clone()),
+        });
+    }
+    markers
+}
+
+// 3x5 glyphs for marker labels.  Each row is the low three bits of a byte.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0x7, 0x5, 0x5, 0x5, 0x7],
+        '1' => [0x2, 0x6, 0x2, 0x2, 0x7],
+        '2' => [0x7, 0x1, 0x7, 0x4, 0x7],
+        '3' => [0x7, 0x1, 0x3, 0x1, 0x7],
+        '4' => [0x5, 0x5, 0x7, 0x1, 0x1],
+        '5' => [0x7, 0x4, 0x7, 0x1, 0x7],
+        '6' => [0x7, 0x4, 0x7, 0x5, 0x7],
+        '7' => [0x7, 0x1, 0x1, 0x1, 0x1],
+        '8' => [0x7, 0x5, 0x7, 0x5, 0x7],
+        '9' => [0x7, 0x5, 0x7, 0x1, 0x7],
+        'A' => [0x2, 0x5, 0x7, 0x5, 0x5],
+        'B' => [0x6, 0x5, 0x6, 0x5, 0x6],
+        'C' => [0x3, 0x4, 0x4, 0x4, 0x3],
+        'D' => [0x6, 0x5, 0x5, 0x5, 0x6],
+        'E' => [0x7, 0x4, 0x6, 0x4, 0x7],
+        'F' => [0x7, 0x4, 0x6, 0x4, 0x4],
+        'N' => [0x5, 0x7, 0x7, 0x5, 0x5],
+        'T' => [0x7, 0x2, 0x2, 0x2, 0x2],
+        'X' => [0x5, 0x5, 0x2, 0x5, 0x5],
+        _ => [0x0; 5],
+    }
+}
+
+fn draw_text(image: &mut Image, text: &str, x: usize, y: usize, c: Color) {
+    let black = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0xff,
+    };
+    image.fill_rect(x, y, text.len() * 4 + 1, 7, black);
+    for (i, ch) in text.chars().enumerate() {
+        let g = glyph(ch);
+        for (row, bits) in g.iter().enumerate() {
+            for col in 0..3 {
+                if (bits >> (2 - col)) & 0x1 == 0x1 {
+                    image.set_pixel(x + 1 + i * 4 + col, y + 1 + row, c);
+                }
+            }
+        }
+    }
+}
+
+fn draw_outline(image: &mut Image, marker: &Marker) {
+    let c = marker.color();
+    let x = marker.x * TILE_SIZE;
+    let y = marker.y * TILE_SIZE;
+    let w = marker.w * TILE_SIZE;
+    let h = marker.h * TILE_SIZE;
+    image.draw_rect(x, y, w, h, c);
+    image.draw_rect(x + 1, y + 1, w - 2, h - 2, c);
+
+    // Start direction is drawn as a tick on the facing edge.
+    match marker.direction {
+        Some(0) => image.fill_rect(x + w / 2 - 2, y, 4, 4, c),
+        Some(1) => image.fill_rect(x + w - 4, y + h / 2 - 2, 4, 4, c),
+        Some(2) => image.fill_rect(x + w / 2 - 2, y + h - 4, 4, 4, c),
+        Some(3) => image.fill_rect(x, y + h / 2 - 2, 4, 4, c),
+        _ => (),
+    }
+}
+
+// Draws an NPC's standing pose for its start direction.  Sprites are 16x24
+// and stand on their tile, so they extend half a tile above it.
+fn draw_npc(
+    rom_data: &[u8],
+    image: &mut Image,
+    marker: &Marker,
+    location: &Location,
+) -> Result<(), Box<Error>> {
+    let npc = &location.npcs[marker.index];
+    let (pose, h_flip) = sprite::facing_pose(npc.start_direction);
+    let tiles = sprite::pose_tiles(rom_data, npc.sprite as usize, pose)?;
+    let colors = palette::read(rom_data, Kind::Sprite, npc.palette as usize)?;
+    let x = marker.x * TILE_SIZE;
+    let y = (marker.y * TILE_SIZE + TILE_SIZE).saturating_sub(sprite::POSE_HEIGHT);
+    for (i, t) in tiles.iter().enumerate() {
+        let column = if h_flip { 1 - i % 2 } else { i % 2 };
+        image.draw_tile(
+            t,
+            &colors.colors,
+            x + column * SPRITE_TILE_SIZE,
+            y + (i / 2) * SPRITE_TILE_SIZE,
+            h_flip,
+            false,
+        );
+    }
+    Ok(())
+}
+
+// NPCs are drawn with their sprites and everything else as outlines.  With
+// `outline` set, or when an NPC's sprite can't be decoded, NPCs are outlined
+// as well.
+pub fn annotate(rom_data: &[u8], image: &Image, location: &Location, outline: bool) -> Image {
+    let mut annotated = image.clone();
+    for marker in markers(location) {
+        let drawn = marker.kind == MarkerKind::Npc
+            && !outline
+            && draw_npc(rom_data, &mut annotated, &marker, location).is_ok();
+        if !drawn {
+            draw_outline(&mut annotated, &marker);
+        }
+        draw_text(
+            &mut annotated,
+            &marker.label(),
+            marker.x * TILE_SIZE + 2,
+            marker.y * TILE_SIZE + 4,
+            marker.color(),
+        );
+    }
+    annotated
+}
+
+pub fn legend(location: &Location) -> String {
+    let mut text = String::new();
+    for marker in markers(location) {
+        let kind = match marker.kind {
+            MarkerKind::Npc => "npc",
+            MarkerKind::Trigger => "trigger",
+            MarkerKind::Exit => "exit",
+        };
+        let target = match marker.dest {
+            Some(ref d) => format!("map 0x{:03x} ({}, {})", d.map, d.x, d.y),
+            None => format!("event 0x{:06x}", marker.event_addr),
+        };
+        text.push_str(&format!(
+            "{:<4} {:<8} ({:3}, {:3}) {}x{} {}\n",
+            marker.label(),
+            kind,
+            marker.x,
+            marker.y,
+            marker.w,
+            marker.h,
+            target
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use field::npc::Npc;
+    use field::trigger::Trigger;
+
+    #[test]
+    fn legend_test() {
+        let location = Location {
+            properties: ::field::properties::test_properties(),
+            entrance_event_addr: 0,
+            triggers: vec![Trigger {
+                x: 64,
+                y: 14,
+                event_addr: 0x238f2,
+            }],
+            npcs: Vec::new(),
+            short_exits: Vec::new(),
+            long_exits: Vec::new(),
+            treasures: Vec::new(),
+        };
+        assert_eq!(
+            "T0   trigger  ( 64,  14) 1x1 event 0x0238f2\n",
+            legend(&location)
+        );
+
+        let image = Image::new(0x50 * TILE_SIZE, 0x20 * TILE_SIZE);
+        let annotated = annotate(&[], &image, &location, false);
+        assert_eq!(
+            markers(&location)[0].color(),
+            annotated.get_pixel(64 * TILE_SIZE, 14 * TILE_SIZE)
+        );
+
+        // NPCs whose sprites can't be decoded fall back to outlines.
+        let mut location = location;
+        location.npcs.push(Npc {
+            event_addr: 0,
+            palette: 0,
+            solid_action_path: false,
+            enable_bit: 0,
+            enable_addr: 0,
+            x: 2,
+            show_rider_in_vehicle: false,
+            y: 3,
+            speed: 0,
+            sprite: 0,
+            movement_type: 0,
+            map_layer: 0,
+            vehicle: 0,
+            start_direction: 2,
+            turn_when_triggered: false,
+            unknown_8_bits: 0,
+        });
+        let annotated = annotate(&[], &image, &location, false);
+        assert_eq!(
+            markers(&location)[0].color(),
+            annotated.get_pixel(2 * TILE_SIZE, 3 * TILE_SIZE)
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use compression;
+use field::annotate::{self, Marker, MarkerKind};
+use field::render;
+use field::Location;
+use rom_map;
+use utils::{check_len, test_bit};
+
+const NUM_TILES: usize = 0x100;
+
+// Properties of a single 16x16 map tile.  The decompressed table is the
+// first byte of every tile followed by the second byte of every tile.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct TileProperties {
+    pub upper_z_level: bool,
+    pub lower_z_level: bool,
+    pub top_sprite_priority: bool,
+    pub bottom_sprite_priority: bool,
+    pub door: bool,
+    pub stairs_up_right: bool,
+    pub stairs_up_left: bool,
+    pub unknown_0_7: bool,
+    pub passable_right: bool,
+    pub passable_left: bool,
+    pub passable_down: bool,
+    pub passable_up: bool,
+    pub unknown_1_4: bool,
+    pub unknown_1_5: bool,
+    pub counter: bool,
+    pub random_encounters: bool,
+}
+
+impl TileProperties {
+    // Tiles on both z-levels can be walked over and under.
+    pub fn is_bridge(&self) -> bool {
+        self.upper_z_level && self.lower_z_level && !self.top_sprite_priority
+    }
+
+    // The game treats both z-levels plus top sprite priority as a solid tile.
+    pub fn is_impassable(&self) -> bool {
+        self.upper_z_level && self.lower_z_level && self.top_sprite_priority
+    }
+
+    pub fn is_walkable(&self) -> bool {
+        !self.is_impassable()
+            && (self.passable_right || self.passable_left || self.passable_down || self.passable_up)
+    }
+}
+
+pub fn decode_tile(b0: u8, b1: u8) -> TileProperties {
+    TileProperties {
+        upper_z_level: test_bit(b0, 0),
+        lower_z_level: test_bit(b0, 1),
+        top_sprite_priority: test_bit(b0, 2),
+        bottom_sprite_priority: test_bit(b0, 3),
+        door: test_bit(b0, 4),
+        stairs_up_right: test_bit(b0, 5),
+        stairs_up_left: test_bit(b0, 6),
+        unknown_0_7: test_bit(b0, 7),
+        passable_right: test_bit(b1, 0),
+        passable_left: test_bit(b1, 1),
+        passable_down: test_bit(b1, 2),
+        passable_up: test_bit(b1, 3),
+        unknown_1_4: test_bit(b1, 4),
+        unknown_1_5: test_bit(b1, 5),
+        counter: test_bit(b1, 6),
+        random_encounters: test_bit(b1, 7),
+    }
+}
+
+fn bits(flags: &[bool]) -> u8 {
+    flags
+        .iter()
+        .enumerate()
+        .fold(0, |b, (i, f)| if *f { b | 1 << i } else { b })
+}
+
+pub fn encode_tile(p: &TileProperties) -> (u8, u8) {
+    (
+        bits(&[
+            p.upper_z_level,
+            p.lower_z_level,
+            p.top_sprite_priority,
+            p.bottom_sprite_priority,
+            p.door,
+            p.stairs_up_right,
+            p.stairs_up_left,
+            p.unknown_0_7,
+        ]),
+        bits(&[
+            p.passable_right,
+            p.passable_left,
+            p.passable_down,
+            p.passable_up,
+            p.unknown_1_4,
+            p.unknown_1_5,
+            p.counter,
+            p.random_encounters,
+        ]),
+    )
+}
+
+pub fn decode(data: &[u8]) -> Vec<TileProperties> {
+    (0..NUM_TILES)
+        .map(|i| {
+            let b0 = data.get(i).cloned().unwrap_or(0);
+            let b1 = data.get(NUM_TILES + i).cloned().unwrap_or(0);
+            decode_tile(b0, b1)
+        })
+        .collect()
+}
+
+pub fn encode(tiles: &[TileProperties]) -> Vec<u8> {
+    let mut data = vec![0u8; NUM_TILES * 2];
+    for (i, p) in tiles.iter().take(NUM_TILES).enumerate() {
+        let (b0, b1) = encode_tile(p);
+        data[i] = b0;
+        data[NUM_TILES + i] = b1;
+    }
+    data
+}
+
+// Tile property tables are compressed and found through a table of 16 bit
+// offsets.
+pub fn read(rom_data: &[u8], index: usize) -> Result<Vec<TileProperties>, Box<Error>> {
+    if index >= rom_map::NUM_MAP_TILE_PROPERTIES {
+        return Err(SimpleError::new(format!(
+            "Tile properties {} larger than 0x{:x}",
+            index,
+            rom_map::NUM_MAP_TILE_PROPERTIES - 1
+        ))
+        .into());
+    }
+    let ptr = rom_map::snes_to_file(rom_map::MAP_TILE_PROPERTIES_POINTERS) + index * 2;
+    check_len(rom_data, ptr, 2)?;
+    let offset = rom_data[ptr] as usize | (rom_data[ptr + 1] as usize) << 8;
+    let addr = rom_map::snes_to_file(rom_map::MAP_TILE_PROPERTIES) + offset;
+    if rom_data.len() <= addr {
+        return Err(SimpleError::new(format!("Tile properties {} out of range", index)).into());
+    }
+    Ok(decode(&compression::decompress(&rom_data[addr..])?))
+}
+
+// Properties of every BG1 tile of a location.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CollisionMap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<TileProperties>,
+}
+
+impl CollisionMap {
+    pub fn new(tilemap: &render::Tilemap, properties: &[TileProperties]) -> CollisionMap {
+        CollisionMap {
+            width: tilemap.width,
+            height: tilemap.height,
+            tiles: tilemap
+                .tiles
+                .iter()
+                .map(|t| properties.get(*t as usize).cloned().unwrap_or_default())
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&TileProperties> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.tiles.get(y * self.width + x)
+    }
+
+    // Coordinates outside of the map are never walkable.
+    pub fn is_walkable(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).map(|p| p.is_walkable()).unwrap_or(false)
+    }
+}
+
+pub fn collision_map(rom_data: &[u8], location: &Location) -> Result<CollisionMap, Box<Error>> {
+    let properties = read(rom_data, location.properties.tile_properties_index as usize)?;
+    let tilemaps = render::tilemaps(rom_data, location)?;
+    match tilemaps[0] {
+        Some(ref bg1) => Ok(CollisionMap::new(bg1, &properties)),
+        None => Err(SimpleError::new("location has no BG1 tilemap").into()),
+    }
+}
+
+// NPCs and triggers that sit on tiles which can't be walked on.
+pub fn blocked_markers(location: &Location, map: &CollisionMap) -> Vec<Marker> {
+    annotate::markers(location)
+        .into_iter()
+        .filter(|m| m.kind != MarkerKind::Exit && !map.is_walkable(m.x, m.y))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use field::trigger::Trigger;
+
+    #[test]
+    fn tile_test() {
+        let p = decode_tile(0x07, 0x00);
+        assert!(p.is_impassable());
+        assert!(!p.is_walkable());
+        assert_eq!((0x07, 0x00), encode_tile(&p));
+
+        let p = decode_tile(0x13, 0x8f);
+        assert!(p.is_bridge());
+        assert!(p.door);
+        assert!(p.random_encounters);
+        assert!(p.is_walkable());
+        assert_eq!((0x13, 0x8f), encode_tile(&p));
+
+        let data: Vec<u8> = (0..0x200).map(|i| (i * 7) as u8).collect();
+        assert_eq!(data, encode(&decode(&data)));
+    }
+
+    #[test]
+    fn collision_map_test() {
+        let properties = vec![decode_tile(0x01, 0x0f), decode_tile(0x07, 0x00)];
+        let tilemap = render::Tilemap {
+            width: 2,
+            height: 2,
+            tiles: vec![0, 1, 1, 0],
+        };
+        let map = CollisionMap::new(&tilemap, &properties);
+        assert!(map.is_walkable(0, 0));
+        assert!(!map.is_walkable(1, 0));
+        assert!(map.is_walkable(1, 1));
+        assert!(!map.is_walkable(2, 0));
+
+        let location = Location {
+            properties: ::field::properties::test_properties(),
+            entrance_event_addr: 0,
+            triggers: vec![
+                Trigger {
+                    x: 0,
+                    y: 0,
+                    event_addr: 0,
+                },
+                Trigger {
+                    x: 0,
+                    y: 1,
+                    event_addr: 0,
+                },
+            ],
+            npcs: Vec::new(),
+            short_exits: Vec::new(),
+            long_exits: Vec::new(),
+            treasures: Vec::new(),
+        };
+        let blocked = blocked_markers(&location, &map);
+        assert_eq!(1, blocked.len());
+        assert_eq!(MarkerKind::Trigger, blocked[0].kind);
+        assert_eq!(1, blocked[0].index);
+    }
+}
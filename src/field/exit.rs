@@ -0,0 +1,245 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use ptr_table;
+use rom_map;
+use utils::{check_len, get_u16, test_bit};
+
+const SHORT_EXIT_SIZE: usize = 6;
+const LONG_EXIT_SIZE: usize = 7;
+const NUM_ENTRIES: usize = 0x1a0;
+
+// Where an exit leads.  Map 0x1ff returns to the parent map, usually the
+// world map location the player entered from.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Destination {
+    pub map: u16,
+    pub set_parent_map: bool,
+    pub z_level: bool,
+    pub show_name: bool,
+    pub facing: u8,
+    pub unknown_2_bits: u8,
+    pub x: u8,
+    pub y: u8,
+}
+
+// A single tile exit.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ShortExit {
+    pub x: u8,
+    pub y: u8,
+    pub dest: Destination,
+}
+
+// A horizontal or vertical strip of exit tiles.  It covers length + 1
+// tiles starting at x, y.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct LongExit {
+    pub x: u8,
+    pub y: u8,
+    pub length: u8,
+    pub vertical: bool,
+    pub dest: Destination,
+}
+
+pub fn short_ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
+    ptr_table_at(rom_data, rom_map::SHORT_EXIT_POINTERS)
+}
+
+pub fn long_ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
+    ptr_table_at(rom_data, rom_map::LONG_EXIT_POINTERS)
+}
+
+// Both tables are 16 bit offsets from the start of the table.
+fn ptr_table_at(rom_data: &[u8], snes_addr: usize) -> Result<ptr_table::Table, Box<Error>> {
+    let addr = rom_map::snes_to_file(snes_addr);
+    check_len(rom_data, addr, NUM_ENTRIES * 2)?;
+    Ok(ptr_table::Table::new(&rom_data[addr..], NUM_ENTRIES, addr))
+}
+
+pub fn parse_destination(data: &[u8]) -> Destination {
+    let map = get_u16(data);
+    Destination {
+        map: map & 0x1ff,
+        set_parent_map: test_bit(data[1], 1),
+        z_level: test_bit(data[1], 2),
+        show_name: test_bit(data[1], 3),
+        facing: (data[1] >> 4) & 0x3,
+        unknown_2_bits: data[1] >> 6,
+        x: data[2],
+        y: data[3],
+    }
+}
+
+pub fn encode_destination(dest: &Destination) -> [u8; 4] {
+    let mut hi = (dest.map >> 8) as u8 & 0x1;
+    hi |= (dest.set_parent_map as u8) << 1;
+    hi |= (dest.z_level as u8) << 2;
+    hi |= (dest.show_name as u8) << 3;
+    hi |= (dest.facing & 0x3) << 4;
+    hi |= (dest.unknown_2_bits & 0x3) << 6;
+    [dest.map as u8, hi, dest.x, dest.y]
+}
+
+pub fn parse_short_exits(data: &[u8]) -> Result<Vec<ShortExit>, Box<Error>> {
+    if !data.len().is_multiple_of(SHORT_EXIT_SIZE) {
+        return Err(SimpleError::new(format!(
+            "short exit data length {} not a multiple of {}",
+            data.len(),
+            SHORT_EXIT_SIZE
+        ))
+        .into());
+    }
+    Ok(data
+        .chunks(SHORT_EXIT_SIZE)
+        .map(|d| ShortExit {
+            x: d[0],
+            y: d[1],
+            dest: parse_destination(&d[2..]),
+        })
+        .collect())
+}
+
+pub fn encode_short_exits(exits: &[ShortExit]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for e in exits {
+        data.push(e.x);
+        data.push(e.y);
+        data.extend_from_slice(&encode_destination(&e.dest));
+    }
+    data
+}
+
+pub fn parse_long_exits(data: &[u8]) -> Result<Vec<LongExit>, Box<Error>> {
+    if !data.len().is_multiple_of(LONG_EXIT_SIZE) {
+        return Err(SimpleError::new(format!(
+            "long exit data length {} not a multiple of {}",
+            data.len(),
+            LONG_EXIT_SIZE
+        ))
+        .into());
+    }
+    Ok(data
+        .chunks(LONG_EXIT_SIZE)
+        .map(|d| LongExit {
+            x: d[0],
+            y: d[1],
+            length: d[2] & 0x7f,
+            vertical: test_bit(d[2], 7),
+            dest: parse_destination(&d[3..]),
+        })
+        .collect())
+}
+
+pub fn encode_long_exits(exits: &[LongExit]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for e in exits {
+        data.push(e.x);
+        data.push(e.y);
+        data.push(e.length & 0x7f | (e.vertical as u8) << 7);
+        data.extend_from_slice(&encode_destination(&e.dest));
+    }
+    data
+}
+
+fn write_table(
+    rom_data: &mut [u8],
+    snes_addr: usize,
+    entries: &[Vec<u8>],
+) -> Result<(), Box<Error>> {
+    if entries.len() != NUM_ENTRIES - 1 {
+        return Err(SimpleError::new(format!(
+            "expected {} exit lists.  Got {}.",
+            NUM_ENTRIES - 1,
+            entries.len()
+        ))
+        .into());
+    }
+    let addr = rom_map::snes_to_file(snes_addr);
+    ptr_table::write_u16(rom_data, addr, addr, entries)
+}
+
+// Writes every location's short exits back to the ROM.
+pub fn write_short_exits(rom_data: &mut [u8], exits: &[Vec<ShortExit>]) -> Result<(), Box<Error>> {
+    let entries: Vec<Vec<u8>> = exits.iter().map(|e| encode_short_exits(e)).collect();
+    write_table(rom_data, rom_map::SHORT_EXIT_POINTERS, &entries)
+}
+
+pub fn write_long_exits(rom_data: &mut [u8], exits: &[Vec<LongExit>]) -> Result<(), Box<Error>> {
+    let entries: Vec<Vec<u8>> = exits.iter().map(|e| encode_long_exits(e)).collect();
+    write_table(rom_data, rom_map::LONG_EXIT_POINTERS, &entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_exit_test() {
+        let data = [0x12, 0x0a, 0x06, 0x28, 0x40, 0x1c];
+        let exits = vec![ShortExit {
+            x: 0x12,
+            y: 0x0a,
+            dest: Destination {
+                map: 0x006,
+                set_parent_map: false,
+                z_level: false,
+                show_name: true,
+                facing: 2,
+                unknown_2_bits: 0,
+                x: 0x40,
+                y: 0x1c,
+            },
+        }];
+        assert_eq!(exits, parse_short_exits(&data).unwrap());
+        assert_eq!(data.to_vec(), encode_short_exits(&exits));
+        assert!(parse_short_exits(&data[..5]).is_err());
+    }
+
+    #[test]
+    fn long_exit_test() {
+        let data = [0x20, 0x3f, 0x84, 0xff, 0x03, 0x50, 0x60];
+        let exits = parse_long_exits(&data).unwrap();
+        assert_eq!(4, exits[0].length);
+        assert!(exits[0].vertical);
+        assert_eq!(0x1ff, exits[0].dest.map);
+        assert!(exits[0].dest.set_parent_map);
+        assert_eq!(data.to_vec(), encode_long_exits(&exits));
+    }
+
+    #[test]
+    fn write_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let addr = rom_map::snes_to_file(rom_map::SHORT_EXIT_POINTERS);
+        let start = NUM_ENTRIES * 2;
+        for i in 0..NUM_ENTRIES {
+            let offset = start + if i == NUM_ENTRIES - 1 { 12 } else { 0 };
+            rom[addr + i * 2] = offset as u8;
+            rom[addr + i * 2 + 1] = (offset >> 8) as u8;
+        }
+
+        let mut exits = vec![Vec::new(); NUM_ENTRIES - 1];
+        exits[3] = parse_short_exits(&[0x12, 0x0a, 0x06, 0x28, 0x40, 0x1c]).unwrap();
+        write_short_exits(&mut rom, &exits).unwrap();
+        let table = short_ptr_table(&rom).unwrap();
+        for (i, e) in exits.iter().enumerate() {
+            let data = table.entries[i].slice(&rom).unwrap();
+            assert_eq!(*e, parse_short_exits(data).unwrap());
+        }
+
+        // Shrinking keeps the region so the original data fits again.
+        exits[3].clear();
+        write_short_exits(&mut rom, &exits).unwrap();
+        exits[3] = parse_short_exits(&[0x12, 0x0a, 0x06, 0x28, 0x40, 0x1c]).unwrap();
+        exits[4] = exits[3].clone();
+        write_short_exits(&mut rom, &exits).unwrap();
+        let table = short_ptr_table(&rom).unwrap();
+        assert_eq!(
+            exits[4],
+            parse_short_exits(table.entries[4].slice(&rom).unwrap()).unwrap()
+        );
+
+        exits[5] = exits[3].clone();
+        assert!(write_short_exits(&mut rom, &exits).is_err());
+    }
+}
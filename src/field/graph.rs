@@ -0,0 +1,308 @@
+use serde_json;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::error::Error;
+
+use events;
+use field::exit::{self, Destination};
+use field::Location;
+use rom_map;
+
+// Exits and map loads to this map go back to the map the player entered
+// from.
+pub const PARENT_MAP: u16 = 0x1ff;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum EdgeKind {
+    ShortExit,
+    LongExit,
+    // A world character script LoadMap event.  `index` is the script
+    // address.
+    Event,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Edge {
+    pub from: u16,
+    pub to: u16,
+    pub kind: EdgeKind,
+    pub index: usize,
+    pub dest: Destination,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Graph {
+    pub num_locations: usize,
+    pub edges: Vec<Edge>,
+}
+
+fn exit_edges(locations: &[Location]) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for (l, location) in locations.iter().enumerate() {
+        for (i, e) in location.short_exits.iter().enumerate() {
+            edges.push(Edge {
+                from: l as u16,
+                to: e.dest.map,
+                kind: EdgeKind::ShortExit,
+                index: i,
+                dest: e.dest.clone(),
+            });
+        }
+        for (i, e) in location.long_exits.iter().enumerate() {
+            edges.push(Edge {
+                from: l as u16,
+                to: e.dest.map,
+                kind: EdgeKind::LongExit,
+                index: i,
+                dest: e.dest.clone(),
+            });
+        }
+    }
+    edges
+}
+
+// Entrance, trigger, and NPC scripts that parse as world character scripts
+// contribute their LoadMap events.  Field scripts are skipped since the
+// field event parser does not decode map loads.
+fn event_edges(rom_data: &[u8], locations: &[Location]) -> Vec<Edge> {
+    let base = rom_map::snes_to_file(rom_map::EVENT_SCRIPTS);
+    let mut edges = Vec::new();
+    for (l, location) in locations.iter().enumerate() {
+        let mut addrs = vec![location.entrance_event_addr];
+        addrs.extend(location.triggers.iter().map(|t| t.event_addr));
+        addrs.extend(location.npcs.iter().map(|n| n.event_addr));
+        addrs.sort();
+        addrs.dedup();
+
+        for addr in addrs {
+            let start = base + addr as usize;
+            if start >= rom_data.len() {
+                continue;
+            }
+            let loads = match events::world_char_map_loads(&rom_data[start..]) {
+                Ok(loads) => loads,
+                Err(_) => continue,
+            };
+            for (map, x, y) in loads {
+                let dest = exit::parse_destination(&[map as u8, (map >> 8) as u8, x, y]);
+                edges.push(Edge {
+                    from: l as u16,
+                    to: dest.map,
+                    kind: EdgeKind::Event,
+                    index: addr as usize,
+                    dest: dest,
+                });
+            }
+        }
+    }
+    edges
+}
+
+// Replaces edges to PARENT_MAP with edges to every map which enters `from`
+// while setting the parent map.  Edges with no known parent are dropped.
+fn resolve_parents(edges: Vec<Edge>) -> Vec<Edge> {
+    let mut parents: BTreeMap<u16, BTreeSet<u16>> = BTreeMap::new();
+    for e in &edges {
+        if e.dest.set_parent_map && e.to != PARENT_MAP {
+            parents.entry(e.to).or_default().insert(e.from);
+        }
+    }
+
+    let mut resolved = Vec::new();
+    for e in edges {
+        if e.to != PARENT_MAP {
+            resolved.push(e);
+            continue;
+        }
+        if let Some(p) = parents.get(&e.from) {
+            for to in p {
+                let mut edge = e.clone();
+                edge.to = *to;
+                resolved.push(edge);
+            }
+        }
+    }
+    resolved
+}
+
+impl Graph {
+    pub fn new(locations: &[Location]) -> Graph {
+        Graph {
+            num_locations: locations.len(),
+            edges: resolve_parents(exit_edges(locations)),
+        }
+    }
+
+    // Includes map loads from event scripts as well as exits.
+    pub fn from_rom(rom_data: &[u8], locations: &[Location]) -> Graph {
+        let mut edges = exit_edges(locations);
+        edges.extend(event_edges(rom_data, locations));
+        Graph {
+            num_locations: locations.len(),
+            edges: resolve_parents(edges),
+        }
+    }
+
+    pub fn edges_from(&self, map: u16) -> Vec<&Edge> {
+        self.edges.iter().filter(|e| e.from == map).collect()
+    }
+
+    // Breadth first search for the route with the fewest map transitions.
+    pub fn shortest_route(&self, from: u16, to: u16) -> Option<Vec<&Edge>> {
+        let mut prev: BTreeMap<u16, &Edge> = BTreeMap::new();
+        let mut visited = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(from);
+        queue.push_back(from);
+
+        while let Some(map) = queue.pop_front() {
+            if map == to {
+                let mut route = Vec::new();
+                let mut m = to;
+                while m != from {
+                    let e = prev[&m];
+                    route.push(e);
+                    m = e.from;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for e in self.edges_from(map) {
+                if visited.insert(e.to) {
+                    prev.insert(e.to, e);
+                    queue.push_back(e.to);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn reachable(&self, start: u16) -> BTreeSet<u16> {
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![start];
+        while let Some(map) = stack.pop() {
+            if !visited.insert(map) {
+                continue;
+            }
+            for e in self.edges_from(map) {
+                stack.push(e.to);
+            }
+        }
+        visited
+    }
+
+    pub fn unreachable(&self, start: u16) -> Vec<u16> {
+        let reachable = self.reachable(start);
+        (0..self.num_locations as u16)
+            .filter(|m| !reachable.contains(m))
+            .collect()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut text = String::from("digraph locations {\n");
+        for l in 0..self.num_locations {
+            text.push_str(&format!("    m{:03x} [label=\"{:03x}\"];\n", l, l));
+        }
+        for e in &self.edges {
+            let (label, style) = match e.kind {
+                EdgeKind::ShortExit => (format!("S{}", e.index), "solid"),
+                EdgeKind::LongExit => (format!("L{}", e.index), "solid"),
+                EdgeKind::Event => (format!("{:06x}", e.index), "dashed"),
+            };
+            text.push_str(&format!(
+                "    m{:03x} -> m{:03x} [label=\"{}\", style={}];\n",
+                e.from, e.to, label, style
+            ));
+        }
+        text.push_str("}\n");
+        text
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use field::exit::{LongExit, ShortExit};
+    use field::properties;
+
+    fn dest(map: u16, set_parent_map: bool) -> Destination {
+        Destination {
+            map: map,
+            set_parent_map: set_parent_map,
+            z_level: false,
+            show_name: false,
+            facing: 0,
+            unknown_2_bits: 0,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    fn location(short_exits: Vec<ShortExit>, long_exits: Vec<LongExit>) -> Location {
+        Location {
+            properties: properties::test_properties(),
+            entrance_event_addr: 0,
+            triggers: Vec::new(),
+            npcs: Vec::new(),
+            short_exits: short_exits,
+            long_exits: long_exits,
+            treasures: Vec::new(),
+        }
+    }
+
+    fn short_exit(map: u16, set_parent_map: bool) -> ShortExit {
+        ShortExit {
+            x: 0,
+            y: 0,
+            dest: dest(map, set_parent_map),
+        }
+    }
+
+    #[test]
+    fn route_test() {
+        // 0 -> 1 (setting the parent), 1 -> parent, 1 -> 2, 3 is isolated.
+        let locations = vec![
+            location(vec![short_exit(1, true)], Vec::new()),
+            location(
+                vec![short_exit(PARENT_MAP, false)],
+                vec![LongExit {
+                    x: 0,
+                    y: 0,
+                    length: 2,
+                    vertical: false,
+                    dest: dest(2, false),
+                }],
+            ),
+            location(Vec::new(), Vec::new()),
+            location(Vec::new(), Vec::new()),
+        ];
+        let graph = Graph::new(&locations);
+        assert_eq!(3, graph.edges.len());
+
+        let route = graph.shortest_route(0, 2).unwrap();
+        assert_eq!(2, route.len());
+        assert_eq!(EdgeKind::LongExit, route[1].kind);
+        assert_eq!(0, graph.shortest_route(2, 2).unwrap().len());
+        assert_eq!(
+            vec![1, 0],
+            graph
+                .shortest_route(1, 0)
+                .unwrap()
+                .iter()
+                .flat_map(|e| vec![e.from, e.to])
+                .collect::<Vec<u16>>()
+        );
+        assert!(graph.shortest_route(2, 0).is_none());
+        assert_eq!(vec![3], graph.unreachable(0));
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("m001 -> m000 [label=\"S0\", style=solid];"));
+        assert_eq!(
+            graph,
+            serde_json::from_str::<Graph>(&graph.to_json().unwrap()).unwrap()
+        );
+    }
+}
@@ -1,26 +1,40 @@
 use std::error::Error;
 
 use rom_map;
-use utils::get_u24;
+use utils::{check_len, get_u24};
 
+pub mod annotate;
+pub mod collision;
+pub mod exit;
+pub mod graph;
 pub mod npc;
 pub mod properties;
+pub mod render;
+pub mod sprite;
+pub mod tmx;
+pub mod treasure;
 pub mod trigger;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Location {
-    properties: properties::Properties,
-    entrance_event_addr: u32,
-    triggers: Vec<trigger::Trigger>,
-    npcs: Vec<npc::Npc>,
+    pub properties: properties::Properties,
+    pub entrance_event_addr: u32,
+    pub triggers: Vec<trigger::Trigger>,
+    pub npcs: Vec<npc::Npc>,
+    pub short_exits: Vec<exit::ShortExit>,
+    pub long_exits: Vec<exit::LongExit>,
+    pub treasures: Vec<treasure::Treasure>,
 }
 
 pub fn parse(rom_data: &[u8]) -> Result<Vec<Location>, Box<Error>> {
     let mut locs = Vec::new();
     let npc_table = npc::ptr_table(&rom_data)?;
     let trigger_table = trigger::ptr_table(&rom_data)?;
+    let short_exit_table = exit::short_ptr_table(&rom_data)?;
+    let long_exit_table = exit::long_ptr_table(&rom_data)?;
+    let treasure_table = treasure::ptr_table(&rom_data)?;
 
-    for l in 0..0x19f {
+    for l in 0..rom_map::NUM_LOCATIONS {
         let properties = properties::parse(properties::data(l, &rom_data)?)?;
 
         let entrance_table = rom_map::snes_to_file(rom_map::LOCATION_ENTRANCE_EVENTS) + l * 3;
@@ -32,13 +46,33 @@ pub fn parse(rom_data: &[u8]) -> Result<Vec<Location>, Box<Error>> {
         let trigger_entry = &trigger_table.entries[l];
         let triggers = trigger::parse_triggers(trigger_entry.slice(&rom_data)?)?;
 
+        let short_exits = exit::parse_short_exits(short_exit_table.entries[l].slice(&rom_data)?)?;
+        let long_exits = exit::parse_long_exits(long_exit_table.entries[l].slice(&rom_data)?)?;
+
+        let treasures = treasure::parse_treasures(treasure_table.entries[l].slice(&rom_data)?)?;
+
         locs.push(Location {
             properties: properties,
             entrance_event_addr: entrance_event,
             triggers: triggers,
             npcs: npcs,
+            short_exits: short_exits,
+            long_exits: long_exits,
+            treasures: treasures,
         });
     }
 
     Ok(locs)
 }
+
+// Writes the entrance event address of every location back to the ROM.
+pub fn write_entrance_events(rom_data: &mut [u8], addrs: &[u32]) -> Result<(), Box<Error>> {
+    let table = rom_map::snes_to_file(rom_map::LOCATION_ENTRANCE_EVENTS);
+    check_len(rom_data, table, addrs.len() * 3)?;
+    for (l, addr) in addrs.iter().enumerate() {
+        rom_data[table + l * 3] = *addr as u8;
+        rom_data[table + l * 3 + 1] = (*addr >> 8) as u8;
+        rom_data[table + l * 3 + 2] = (*addr >> 16) as u8;
+    }
+    Ok(())
+}
@@ -5,28 +5,31 @@ use ptr_table;
 use rom_map;
 use utils::{get_u16, get_u24, test_bit};
 
+const NPC_SIZE: usize = 9;
+const NUM_ENTRIES: usize = 0x1a0;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Npc {
-    event_addr: u32,
-    palette: u8,
-    solid_action_path: bool,
+    pub event_addr: u32,
+    pub palette: u8,
+    pub solid_action_path: bool,
 
     // bit and byte offset info the NPC Event bits at $1EE0.  The bit
     // specified controls NPC visibility.
-    enable_bit: u8,
-    enable_addr: u8,
+    pub enable_bit: u8,
+    pub enable_addr: u8,
 
-    x: u8,
-    show_rider_in_vehicle: bool,
-    y: u8,
-    speed: u8,
-    sprite: u8,
-    movement_type: u8, // ZD CE: action
-    map_layer: u8,     // ZD CE: walkUnder, walkOver
-    vehicle: u8,
-    start_direction: u8,       // ZD CE: f
-    turn_when_triggered: bool, // ZD CE: dontFaceOnTrigger
-    unknown_8_bits: u8,
+    pub x: u8,
+    pub show_rider_in_vehicle: bool,
+    pub y: u8,
+    pub speed: u8,
+    pub sprite: u8,
+    pub movement_type: u8, // ZD CE: action
+    pub map_layer: u8,     // ZD CE: walkUnder, walkOver
+    pub vehicle: u8,
+    pub start_direction: u8,       // ZD CE: f
+    pub turn_when_triggered: bool, // ZD CE: dontFaceOnTrigger
+    pub unknown_8_bits: u8,
 }
 
 pub fn ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
@@ -34,14 +37,14 @@ pub fn ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
     let addr = rom_map::snes_to_file(rom_map::NPC_POINTERS);
     let table = ptr_table::Table::new(
         &rom_data[addr..],
-        0x1a0,
+        NUM_ENTRIES,
         rom_map::snes_to_file(rom_map::NPC_POINTERS),
     );
     Ok(table)
 }
 
 pub fn parse_npc(data: &[u8]) -> Result<Npc, Box<Error>> {
-    if data.len() < 0x9 {
+    if data.len() < NPC_SIZE {
         return Err(SimpleError::new("data does not contain at least 0x9 bytes").into());
     }
 
@@ -66,14 +69,54 @@ pub fn parse_npc(data: &[u8]) -> Result<Npc, Box<Error>> {
 }
 
 pub fn parse_npcs(data: &[u8]) -> Result<Vec<Npc>, Box<Error>> {
-    let num = data.len() / 9;
+    let num = data.len() / NPC_SIZE;
     let mut npcs = Vec::new();
     for i in 0..num {
-        npcs.push(parse_npc(&data[(i * 9)..])?);
+        npcs.push(parse_npc(&data[(i * NPC_SIZE)..])?);
     }
     Ok(npcs)
 }
 
+pub fn encode_npc(npc: &Npc) -> [u8; NPC_SIZE] {
+    [
+        npc.event_addr as u8,
+        (npc.event_addr >> 8) as u8,
+        (npc.event_addr >> 16) as u8 & 0x3
+            | (npc.palette & 0x7) << 2
+            | (npc.solid_action_path as u8) << 5
+            | (npc.enable_bit & 0x3) << 6,
+        (npc.enable_bit >> 2) & 0x1 | npc.enable_addr << 1,
+        npc.x & 0x7f | (npc.show_rider_in_vehicle as u8) << 7,
+        npc.y & 0x3f | npc.speed << 6,
+        npc.sprite,
+        npc.movement_type & 0xf | (npc.map_layer & 0x3) << 4 | npc.vehicle << 6,
+        npc.start_direction & 0x3 | (npc.turn_when_triggered as u8) << 2 | npc.unknown_8_bits << 3,
+    ]
+}
+
+pub fn encode_npcs(npcs: &[Npc]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for n in npcs {
+        data.extend_from_slice(&encode_npc(n));
+    }
+    data
+}
+
+// Writes every location's NPCs back to the ROM.
+pub fn write_npcs(rom_data: &mut [u8], npcs: &[Vec<Npc>]) -> Result<(), Box<Error>> {
+    if npcs.len() != NUM_ENTRIES - 1 {
+        return Err(SimpleError::new(format!(
+            "expected {} npc lists.  Got {}.",
+            NUM_ENTRIES - 1,
+            npcs.len()
+        ))
+        .into());
+    }
+    let entries: Vec<Vec<u8>> = npcs.iter().map(|n| encode_npcs(n)).collect();
+    let addr = rom_map::snes_to_file(rom_map::NPC_POINTERS);
+    ptr_table::write_u16(rom_data, addr, addr, &entries)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,7 +128,7 @@ mod tests {
                 event_addr: 184951,
                 palette: 1,
                 solid_action_path: false,
-                enable_bit: 6,
+                enable_bit: 0,
                 enable_addr: 96,
                 x: 64,
                 show_rider_in_vehicle: false,
@@ -103,7 +146,7 @@ mod tests {
                 event_addr: 184999,
                 palette: 1,
                 solid_action_path: false,
-                enable_bit: 6,
+                enable_bit: 0,
                 enable_addr: 96,
                 x: 8,
                 show_rider_in_vehicle: false,
@@ -123,5 +166,6 @@ mod tests {
             0x66, 0x36, 0x00, 0x02,
         ];
         assert_eq!(npcs, parse_npcs(&data).unwrap());
+        assert_eq!(data.to_vec(), encode_npcs(&npcs));
     }
 }
@@ -13,51 +13,51 @@ pub enum BgDimension {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Properties {
-    name_index: u8,
-    enable_x_zone: bool,
-    enable_warp: bool,
-    wavy_bg3: bool,
-    wavy_bg2: bool,
-    wavy_bg1: bool,
-    unknown_flag_1_6: bool,
-    enable_spotlights: bool,
-    load_timer_graphics: bool,
-    battle_background: u8,
-    bg3_in_foreground: bool,
-    unknown3: u8,
-    tile_properties_index: u8,
-    battle_properties: u8,
-    enable_random_battles: bool,
-    window_mask_settings: u8, // 2 bits?
-    unknown_6_bits: u8,
-    colosseum_house: bool,
-    bg1_bg2_graphics: [u8; 4],
-    bg3_graphics_index: u8,
-    bg1_tileset_index: u8,
-    bg2_tileset_index: u8,
-    bg_tilemap_index: [u16; 3],
-    sprite_overlay_index: u8,
-    bg2_shift_left: u8,
-    bg2_shift_up: u8,
-    bg3_shift_left: u8,
-    bg3_shift_up: u8,
-    bg2_bg3_scroll_mode: u8,
-    bg1_h: BgDimension,
-    bg1_w: BgDimension,
-    bg2_h: BgDimension,
-    bg2_w: BgDimension,
-    bg3_h: BgDimension,
-    bg3_w: BgDimension,
-    unused_18_bits: u8,
-    palette_index: u8,
-    palette_animation_index: u8,
-    bg1_bg2_animation_index: u8,
-    bg3_animation_index: u8,
-    music_track: u8,
-    unknown_1d: u8,
-    map_width: u8,
-    map_height: u8,
-    bg2_bg3_color_math_mode: u8,
+    pub name_index: u8,
+    pub enable_x_zone: bool,
+    pub enable_warp: bool,
+    pub wavy_bg3: bool,
+    pub wavy_bg2: bool,
+    pub wavy_bg1: bool,
+    pub unknown_flag_1_6: bool,
+    pub enable_spotlights: bool,
+    pub load_timer_graphics: bool,
+    pub battle_background: u8,
+    pub bg3_in_foreground: bool,
+    pub unknown3: u8,
+    pub tile_properties_index: u8,
+    pub battle_properties: u8,
+    pub enable_random_battles: bool,
+    pub window_mask_settings: u8, // 2 bits?
+    pub unknown_6_bits: u8,
+    pub colosseum_house: bool,
+    pub bg1_bg2_graphics: [u8; 4],
+    pub bg3_graphics_index: u8,
+    pub bg1_tileset_index: u8,
+    pub bg2_tileset_index: u8,
+    pub bg_tilemap_index: [u16; 3],
+    pub sprite_overlay_index: u8,
+    pub bg2_shift_left: u8,
+    pub bg2_shift_up: u8,
+    pub bg3_shift_left: u8,
+    pub bg3_shift_up: u8,
+    pub bg2_bg3_scroll_mode: u8,
+    pub bg1_h: BgDimension,
+    pub bg1_w: BgDimension,
+    pub bg2_h: BgDimension,
+    pub bg2_w: BgDimension,
+    pub bg3_h: BgDimension,
+    pub bg3_w: BgDimension,
+    pub unused_18_bits: u8,
+    pub palette_index: u8,
+    pub palette_animation_index: u8,
+    pub bg1_bg2_animation_index: u8,
+    pub bg3_animation_index: u8,
+    pub music_track: u8,
+    pub unknown_1d: u8,
+    pub map_width: u8,
+    pub map_height: u8,
+    pub bg2_bg3_color_math_mode: u8,
 }
 
 fn test_bit(data: u8, bit: u8) -> bool {
@@ -146,17 +146,25 @@ pub fn parse(data: &[u8]) -> Result<Properties, Box<Error>> {
     })
 }
 
+// Properties of a real location shared by tests which need a Location.
+#[cfg(test)]
+pub const TEST_DATA: [u8; 0x21] = [
+    0x00, 0x00, 0x30, 0x00, 0x13, 0x80, 0x00, 0xaa, 0xc8, 0x06, 0x00, 0x64, 0x34, 0x03, 0x01, 0x00,
+    0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x0e,
+    0x00,
+];
+
+#[cfg(test)]
+pub fn test_properties() -> Properties {
+    parse(&TEST_DATA).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn simple_test() {
-        let data = [
-            0x00, 0x00, 0x30, 0x00, 0x13, 0x80, 0x00, 0xaa, 0xc8, 0x06, 0x00, 0x64, 0x34, 0x03,
-            0x01, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x0f, 0x00, 0x00,
-            0x00, 0x00, 0x1f, 0x0e, 0x00,
-        ];
         assert_eq!(
             Properties {
                 name_index: 0,
@@ -205,7 +213,7 @@ mod tests {
                 map_height: 14,
                 bg2_bg3_color_math_mode: 0
             },
-            parse(&data).unwrap()
+            parse(&TEST_DATA).unwrap()
         );
     }
 }
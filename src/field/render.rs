@@ -0,0 +1,390 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use compression;
+use field::properties::{BgDimension, Properties};
+use field::Location;
+use graphics::palette::{self, Palette};
+use graphics::tile::{self, Format};
+use graphics::{Color, Image};
+use rom_map;
+use utils::{check_len, get_u24};
+
+// Sizes of the four BG1/BG2 graphics sets as loaded into VRAM.
+const GRAPHICS_SET_SIZES: [usize; 4] = [0x2000, 0x2000, 0x2000, 0x1000];
+const TILESET_SIZE: usize = 0x800;
+const BG3_FORMATION_SIZE: usize = 0x40;
+
+pub struct Layers {
+    pub bg1: Image,
+    pub bg2: Option<Image>,
+    pub bg3: Option<Image>,
+    pub composite: Image,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Tile {
+    index: usize,
+    palette: usize,
+    priority: bool,
+    h_flip: bool,
+    v_flip: bool,
+}
+
+// Each layer is drawn into separate low and high priority images so they
+// can be interleaved when compositing.
+struct Layer {
+    low: Image,
+    high: Image,
+}
+
+impl Layer {
+    fn flatten(&self) -> Image {
+        let mut image = self.low.clone();
+        image.blit(&self.high, 0, 0);
+        image
+    }
+}
+
+// Size of a layer in 16x16 map tiles.
+pub fn bg_tiles(dim: &BgDimension) -> usize {
+    match dim {
+        BgDimension::Bg256 => 16,
+        BgDimension::Bg512 => 32,
+        BgDimension::Bg1024 => 64,
+        BgDimension::Bg2048 => 128,
+    }
+}
+
+fn u24_ptr(rom_data: &[u8], table: usize, base: usize, index: usize) -> Result<usize, Box<Error>> {
+    let addr = rom_map::snes_to_file(table) + index * 3;
+    check_len(rom_data, addr, 3)?;
+    Ok(rom_map::snes_to_file(base) + get_u24(&rom_data[addr..]) as usize)
+}
+
+fn bg1_bg2_graphics(rom_data: &[u8], sets: &[u8; 4]) -> Result<Vec<tile::Tile>, Box<Error>> {
+    let mut tiles = Vec::new();
+    for (set, size) in sets.iter().zip(GRAPHICS_SET_SIZES.iter()) {
+        let addr = u24_ptr(
+            rom_data,
+            rom_map::MAP_GRAPHICS_POINTERS,
+            rom_map::MAP_GRAPHICS,
+            *set as usize,
+        )?;
+        check_len(rom_data, addr, *size)?;
+        tiles.extend(tile::decode(&rom_data[addr..addr + size], Format::Bpp4));
+    }
+    Ok(tiles)
+}
+
+fn tileset(rom_data: &[u8], index: u8) -> Result<Vec<u8>, Box<Error>> {
+    let addr = u24_ptr(
+        rom_data,
+        rom_map::MAP_TILESET_POINTERS,
+        rom_map::MAP_TILESETS,
+        index as usize,
+    )?;
+    let mut data = compression::decompress(&rom_data[addr..])?;
+    data.resize(TILESET_SIZE, 0);
+    Ok(data)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Tilemap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+// Tilemaps are one byte per 16x16 map tile.
+fn tilemap(rom_data: &[u8], index: u16, w: usize, h: usize) -> Result<Tilemap, Box<Error>> {
+    let addr = u24_ptr(
+        rom_data,
+        rom_map::MAP_TILEMAP_POINTERS,
+        rom_map::MAP_TILEMAPS,
+        index as usize,
+    )?;
+    let mut data = compression::decompress(&rom_data[addr..])?;
+    data.resize(w * h, 0);
+    Ok(Tilemap {
+        width: w,
+        height: h,
+        tiles: data,
+    })
+}
+
+// Returns the BG1, BG2, and BG3 tilemaps of a location.  A tilemap index of
+// 0 for BG2 or BG3 means the layer is unused.
+pub fn tilemaps(rom_data: &[u8], location: &Location) -> Result<Vec<Option<Tilemap>>, Box<Error>> {
+    let props = &location.properties;
+    let dims = [
+        (&props.bg1_w, &props.bg1_h),
+        (&props.bg2_w, &props.bg2_h),
+        (&props.bg3_w, &props.bg3_h),
+    ];
+    let mut tilemaps = Vec::new();
+    for (layer, (w, h)) in dims.iter().enumerate() {
+        let index = props.bg_tilemap_index[layer];
+        if layer != 0 && index == 0 {
+            tilemaps.push(None);
+        } else {
+            tilemaps.push(Some(tilemap(rom_data, index, bg_tiles(w), bg_tiles(h))?));
+        }
+    }
+    Ok(tilemaps)
+}
+
+// A tileset is four 0x100 byte tables of tile numbers, one for each 8x8
+// quadrant of the 16x16 map tiles, followed by four tables of attributes.
+fn tileset_tile(tileset: &[u8], map_tile: u8, quadrant: usize) -> Tile {
+    let lo = tileset[quadrant * 0x100 + map_tile as usize];
+    let hi = tileset[0x400 + quadrant * 0x100 + map_tile as usize];
+    Tile {
+        index: lo as usize | (hi as usize & 0x3) << 8,
+        palette: (hi as usize >> 2) & 0x7,
+        priority: (hi & 0x20) == 0x20,
+        h_flip: (hi & 0x40) == 0x40,
+        v_flip: (hi & 0x80) == 0x80,
+    }
+}
+
+// Everything needed to draw the 16x16 map tiles of one layer.
+enum LayerGraphics<'a> {
+    Bg1Bg2 {
+        tileset: Vec<u8>,
+        graphics: &'a [tile::Tile],
+        palette: &'a Palette,
+    },
+    // BG3 graphics start with a table giving the first 2bpp character of
+    // each 16x16 tile.  Tiles use the SNES 16x16 layout of c, c + 1, c + 16,
+    // c + 17.
+    Bg3 {
+        formation: Vec<u8>,
+        graphics: Vec<tile::Tile>,
+        palette: &'a Palette,
+        priority: bool,
+    },
+}
+
+impl<'a> LayerGraphics<'a> {
+    fn bg1_bg2(
+        rom_data: &[u8],
+        tileset_index: u8,
+        graphics: &'a [tile::Tile],
+        palette: &'a Palette,
+    ) -> Result<LayerGraphics<'a>, Box<Error>> {
+        Ok(LayerGraphics::Bg1Bg2 {
+            tileset: tileset(rom_data, tileset_index)?,
+            graphics: graphics,
+            palette: palette,
+        })
+    }
+
+    fn bg3(
+        rom_data: &[u8],
+        props: &Properties,
+        palette: &'a Palette,
+    ) -> Result<LayerGraphics<'a>, Box<Error>> {
+        let addr = u24_ptr(
+            rom_data,
+            rom_map::MAP_BG3_GRAPHICS_POINTERS,
+            rom_map::MAP_BG3_GRAPHICS,
+            props.bg3_graphics_index as usize,
+        )?;
+        let data = compression::decompress(&rom_data[addr..])?;
+        if data.len() < BG3_FORMATION_SIZE {
+            return Err(SimpleError::new("bg3 graphics smaller than formation table").into());
+        }
+        Ok(LayerGraphics::Bg3 {
+            formation: data[..BG3_FORMATION_SIZE].to_vec(),
+            graphics: tile::decode(&data[BG3_FORMATION_SIZE..], Format::Bpp2),
+            palette: palette,
+            priority: props.bg3_in_foreground,
+        })
+    }
+
+    fn tile(&self, map_tile: u8, quadrant: usize) -> Option<(Tile, &tile::Tile, &[Color])> {
+        match self {
+            LayerGraphics::Bg1Bg2 {
+                tileset,
+                graphics,
+                palette,
+            } => {
+                let t = tileset_tile(tileset, map_tile, quadrant);
+                if t.index >= graphics.len() {
+                    return None;
+                }
+                let colors = palette.sub_palette(t.palette, 16);
+                let index = t.index;
+                Some((t, &graphics[index], colors))
+            }
+            LayerGraphics::Bg3 {
+                formation,
+                graphics,
+                palette,
+                priority,
+            } => {
+                let base = formation[map_tile as usize % BG3_FORMATION_SIZE] as usize;
+                let index = base + (quadrant & 0x1) + (quadrant >> 1) * 16;
+                if index >= graphics.len() {
+                    return None;
+                }
+                let t = Tile {
+                    index: index,
+                    palette: 0,
+                    priority: *priority,
+                    h_flip: false,
+                    v_flip: false,
+                };
+                Some((t, &graphics[index], palette.sub_palette(0, 4)))
+            }
+        }
+    }
+}
+
+fn draw_layer(tilemap: &Tilemap, graphics: &LayerGraphics) -> Layer {
+    let (w, h) = (tilemap.width, tilemap.height);
+    let mut layer = Layer {
+        low: Image::new(w * 16, h * 16),
+        high: Image::new(w * 16, h * 16),
+    };
+    for y in 0..h {
+        for x in 0..w {
+            let map_tile = tilemap.tiles[y * w + x];
+            for quadrant in 0..4 {
+                if let Some((t, pixels, palette)) = graphics.tile(map_tile, quadrant) {
+                    let image = if t.priority {
+                        &mut layer.high
+                    } else {
+                        &mut layer.low
+                    };
+                    image.draw_tile(
+                        pixels,
+                        palette,
+                        x * 16 + (quadrant & 0x1) * 8,
+                        y * 16 + (quadrant >> 1) * 8,
+                        t.h_flip,
+                        t.v_flip,
+                    );
+                }
+            }
+        }
+    }
+    layer
+}
+
+// Loads the graphics of each of a location's layers.  Unused layers are None.
+fn with_layer_graphics<F, T>(rom_data: &[u8], location: &Location, f: F) -> Result<T, Box<Error>>
+where
+    F: FnOnce(Color, &[Option<LayerGraphics>]) -> Result<T, Box<Error>>,
+{
+    let props = &location.properties;
+    let palette = palette::read(rom_data, palette::Kind::Map, props.palette_index as usize)?;
+    let graphics = bg1_bg2_graphics(rom_data, &props.bg1_bg2_graphics)?;
+
+    let mut layers = vec![Some(LayerGraphics::bg1_bg2(
+        rom_data,
+        props.bg1_tileset_index,
+        &graphics,
+        &palette,
+    )?)];
+    layers.push(if props.bg_tilemap_index[1] != 0 {
+        Some(LayerGraphics::bg1_bg2(
+            rom_data,
+            props.bg2_tileset_index,
+            &graphics,
+            &palette,
+        )?)
+    } else {
+        None
+    });
+    layers.push(if props.bg_tilemap_index[2] != 0 {
+        Some(LayerGraphics::bg3(rom_data, props, &palette)?)
+    } else {
+        None
+    });
+
+    f(palette.colors[0], &layers)
+}
+
+// Draws all 256 map tiles of each layer's tileset as a 16x16 grid of tiles.
+pub fn tileset_images(
+    rom_data: &[u8],
+    location: &Location,
+) -> Result<Vec<Option<Image>>, Box<Error>> {
+    let all_tiles = Tilemap {
+        width: 16,
+        height: 16,
+        tiles: (0..0x100).map(|t| t as u8).collect(),
+    };
+    with_layer_graphics(rom_data, location, |_, layers| {
+        Ok(layers
+            .iter()
+            .map(|l| l.as_ref().map(|g| draw_layer(&all_tiles, g).flatten()))
+            .collect())
+    })
+}
+
+pub fn render(rom_data: &[u8], location: &Location) -> Result<Layers, Box<Error>> {
+    let tilemaps = tilemaps(rom_data, location)?;
+    with_layer_graphics(rom_data, location, |backdrop, graphics| {
+        let layers: Vec<Option<Layer>> = tilemaps
+            .iter()
+            .zip(graphics.iter())
+            .map(|l| match l {
+                (Some(t), Some(g)) => Some(draw_layer(t, g)),
+                _ => None,
+            })
+            .collect();
+        let bg1 = match layers[0] {
+            Some(ref l) => l,
+            None => return Err(SimpleError::new("location has no bg1").into()),
+        };
+
+        // Composite in SNES mode 1 priority order.  The backdrop is color 0.
+        let mut composite = Image::new(bg1.low.width, bg1.low.height);
+        composite.fill(backdrop);
+        let order = [
+            (2, false),
+            (1, false),
+            (0, false),
+            (1, true),
+            (0, true),
+            (2, true),
+        ];
+        for (layer, high) in order.iter() {
+            if let Some(ref l) = layers[*layer] {
+                composite.blit(if *high { &l.high } else { &l.low }, 0, 0);
+            }
+        }
+
+        Ok(Layers {
+            bg1: bg1.flatten(),
+            bg2: layers[1].as_ref().map(Layer::flatten),
+            bg3: layers[2].as_ref().map(Layer::flatten),
+            composite: composite,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tileset_tile_test() {
+        let mut tileset = vec![0u8; TILESET_SIZE];
+        tileset[0x300 + 0x12] = 0x34;
+        tileset[0x700 + 0x12] = 0xe9;
+        assert_eq!(
+            Tile {
+                index: 0x134,
+                palette: 2,
+                priority: true,
+                h_flip: true,
+                v_flip: true,
+            },
+            tileset_tile(&tileset, 0x12, 3)
+        );
+    }
+}
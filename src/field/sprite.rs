@@ -0,0 +1,218 @@
+use simple_error::SimpleError;
+use std::collections::HashMap;
+use std::error::Error;
+
+use graphics::tile::{self, Format, Sheet, Tile, TILE_SIZE};
+use rom_map;
+use utils::{check_len, get_u16};
+
+// Each pose is 16x24 pixels made from six 8x8 tiles.  Poses are the same
+// indexes used by world_char::Event::GraphicalAction.
+pub const POSE_WIDTH: usize = 16;
+pub const POSE_HEIGHT: usize = 24;
+pub const NUM_POSES: usize = 0x40;
+const TILES_PER_POSE: usize = 6;
+const SHEET_COLUMNS: usize = 8;
+
+// Standing pose and horizontal flip for each NPC start direction: up,
+// right, down, and left.  Facing right is the left pose flipped.
+const FACING_POSES: [(usize, bool); 4] =
+    [(0x04, false), (0x07, true), (0x01, false), (0x07, false)];
+
+// Offsets, in bytes from the start of a sprite's graphics, of each tile of a
+// pose ordered left to right, top to bottom.
+pub type PoseLayout = [usize; TILES_PER_POSE];
+
+pub fn pose_layouts(rom_data: &[u8]) -> Result<Vec<PoseLayout>, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::SPRITE_POSE_LAYOUTS);
+    check_len(rom_data, addr, NUM_POSES * TILES_PER_POSE * 2)?;
+
+    let mut layouts = Vec::new();
+    for pose in 0..NUM_POSES {
+        let mut layout = [0usize; TILES_PER_POSE];
+        for (i, offset) in layout.iter_mut().enumerate() {
+            *offset = get_u16(&rom_data[addr + (pose * TILES_PER_POSE + i) * 2..]) as usize;
+        }
+        layouts.push(layout);
+    }
+    Ok(layouts)
+}
+
+// Returns the file offset and length of the graphics for `sprite`.  The
+// address is split into a table of low words and a table of banks.
+pub fn graphics_range(rom_data: &[u8], sprite: usize) -> Result<(usize, usize), Box<Error>> {
+    if sprite >= rom_map::NUM_SPRITES {
+        return Err(SimpleError::new(format!(
+            "Sprite {} larger than 0x{:x}",
+            sprite,
+            rom_map::NUM_SPRITES - 1
+        ))
+        .into());
+    }
+    let lo_addr = rom_map::snes_to_file(rom_map::SPRITE_GRAPHICS_POINTERS) + sprite * 2;
+    let bank_addr = rom_map::snes_to_file(rom_map::SPRITE_GRAPHICS_BANKS) + sprite * 2;
+    let len_addr = rom_map::snes_to_file(rom_map::SPRITE_GRAPHICS_SIZES) + sprite * 2;
+    check_len(rom_data, lo_addr.max(bank_addr).max(len_addr), 2)?;
+
+    let snes_addr = get_u16(&rom_data[lo_addr..]) as usize | (rom_data[bank_addr] as usize) << 16;
+    let addr = rom_map::snes_to_file(snes_addr);
+    let len = get_u16(&rom_data[len_addr..]) as usize;
+    check_len(rom_data, addr, len)?;
+    Ok((addr, len))
+}
+
+fn pose_origin(pose: usize) -> (usize, usize) {
+    (
+        (pose % SHEET_COLUMNS) * POSE_WIDTH,
+        (pose / SHEET_COLUMNS) * POSE_HEIGHT,
+    )
+}
+
+fn tile_origin(pose: usize, i: usize) -> (usize, usize) {
+    let (x, y) = pose_origin(pose);
+    (x + (i % 2) * TILE_SIZE, y + (i / 2) * TILE_SIZE)
+}
+
+// Lays out every pose of a sprite on a sheet.  Poses which reference tiles
+// past the end of the sprite's graphics are left blank.  Smaller NPC sprites
+// only have a handful of poses.
+pub fn sheet(rom_data: &[u8], sprite: usize) -> Result<Sheet, Box<Error>> {
+    let layouts = pose_layouts(rom_data)?;
+    let (addr, len) = graphics_range(rom_data, sprite)?;
+    let graphics = &rom_data[addr..addr + len];
+    let tile_len = Format::Bpp4.tile_len();
+
+    let rows = NUM_POSES.div_ceil(SHEET_COLUMNS);
+    let mut sheet = Sheet {
+        width: SHEET_COLUMNS * POSE_WIDTH,
+        height: rows * POSE_HEIGHT,
+        pixels: vec![0u8; SHEET_COLUMNS * POSE_WIDTH * rows * POSE_HEIGHT],
+    };
+    for (pose, layout) in layouts.iter().enumerate() {
+        if layout.iter().any(|o| o + tile_len > len) {
+            continue;
+        }
+        for (i, offset) in layout.iter().enumerate() {
+            let t = tile::decode_tile(&graphics[*offset..], Format::Bpp4);
+            let (x, y) = tile_origin(pose, i);
+            sheet.put_tile(&t, x, y);
+        }
+    }
+    Ok(sheet)
+}
+
+pub fn facing_pose(direction: u8) -> (usize, bool) {
+    FACING_POSES[direction as usize & 0x3]
+}
+
+// Decodes the six tiles of one pose, ordered left to right, top to bottom.
+pub fn pose_tiles(rom_data: &[u8], sprite: usize, pose: usize) -> Result<Vec<Tile>, Box<Error>> {
+    let layouts = pose_layouts(rom_data)?;
+    let layout = layouts
+        .get(pose)
+        .ok_or_else(|| SimpleError::new(format!("unknown pose {:02x}", pose)))?;
+    let (addr, len) = graphics_range(rom_data, sprite)?;
+    let tile_len = Format::Bpp4.tile_len();
+    if layout.iter().any(|o| o + tile_len > len) {
+        return Err(
+            SimpleError::new(format!("sprite {:02x} has no pose {:02x}", sprite, pose)).into(),
+        );
+    }
+    Ok(layout
+        .iter()
+        .map(|o| tile::decode_tile(&rom_data[addr + o..], Format::Bpp4))
+        .collect())
+}
+
+// Writes an edited sheet back over a sprite's graphics.  Poses share tiles
+// so every copy of a tile on the sheet must be identical.
+pub fn write_sheet(rom_data: &mut [u8], sprite: usize, sheet: &Sheet) -> Result<(), Box<Error>> {
+    let layouts = pose_layouts(rom_data)?;
+    let (addr, len) = graphics_range(rom_data, sprite)?;
+    let tile_len = Format::Bpp4.tile_len();
+
+    let mut tiles: HashMap<usize, (usize, Tile)> = HashMap::new();
+    for (pose, layout) in layouts.iter().enumerate() {
+        if layout.iter().any(|o| o + tile_len > len) {
+            continue;
+        }
+        for (i, offset) in layout.iter().enumerate() {
+            let (x, y) = tile_origin(pose, i);
+            let t = sheet.get_tile(x, y);
+            if let Some((other_pose, other)) = tiles.get(offset) {
+                if other[..] != t[..] {
+                    return Err(SimpleError::new(format!(
+                        "pose {:02x} and pose {:02x} disagree on shared tile at 0x{:x}",
+                        other_pose, pose, offset
+                    ))
+                    .into());
+                }
+                continue;
+            }
+            tiles.insert(*offset, (pose, t));
+        }
+    }
+
+    for (offset, (_, t)) in tiles {
+        let start = addr + offset;
+        rom_data[start..start + tile_len].copy_from_slice(&tile::encode_tile(&t, Format::Bpp4));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_origin_test() {
+        assert_eq!((0, 0), tile_origin(0, 0));
+        assert_eq!((8, 16), tile_origin(0, 5));
+        assert_eq!((16 * 7 + 8, 8), tile_origin(7, 3));
+        assert_eq!((0, 24), tile_origin(8, 0));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let mut rom = vec![0u8; 0x300000];
+
+        // Sprite 0's graphics at $D50000 with four tiles shared between the
+        // first two poses.  The low word of the pointer is left as 0.
+        let layouts = rom_map::snes_to_file(rom_map::SPRITE_POSE_LAYOUTS);
+        for (i, o) in [
+            0x00, 0x20, 0x40, 0x60, 0x00, 0x20, 0x40, 0x60, 0x00, 0x20, 0x40, 0x60,
+        ]
+        .iter()
+        .enumerate()
+        {
+            rom[layouts + i * 2] = *o as u8;
+        }
+        for pose in 2..NUM_POSES {
+            rom[layouts + pose * 12] = 0xff;
+        }
+        let bank = rom_map::snes_to_file(rom_map::SPRITE_GRAPHICS_BANKS);
+        let size = rom_map::snes_to_file(rom_map::SPRITE_GRAPHICS_SIZES);
+        rom[bank] = 0xd5;
+        rom[size] = 0x80;
+
+        let graphics = rom_map::snes_to_file(0xd50000);
+        for i in 0..0x80 {
+            rom[graphics + i] = (i * 7) as u8;
+        }
+
+        let s = sheet(&rom, 0).unwrap();
+        assert_eq!(s.get_tile(0, 0), s.get_tile(16, 8));
+        assert_eq!(s.get_tile(0, 0), s.get_tile(0, 16));
+
+        let mut edited = rom.clone();
+        for i in 0..0x80 {
+            edited[graphics + i] = 0;
+        }
+        write_sheet(&mut edited, 0, &s).unwrap();
+        assert_eq!(rom, edited);
+
+        let mut bad = s.clone();
+        bad.pixels[0] ^= 0x1;
+        assert!(write_sheet(&mut edited, 0, &bad).is_err());
+    }
+}
@@ -0,0 +1,601 @@
+use simple_error::SimpleError;
+use std::collections::HashMap;
+use std::error::Error;
+use xml::reader::{EventReader, XmlEvent};
+
+use field::exit::{Destination, LongExit, ShortExit};
+use field::npc::Npc;
+use field::render::{bg_tiles, Tilemap};
+use field::trigger::Trigger;
+use field::Location;
+
+const TILE_SIZE: usize = 16;
+const TILESET_COLUMNS: usize = 16;
+const TILESET_TILES: usize = 0x100;
+const LAYER_NAMES: [&str; 3] = ["bg1", "bg2", "bg3"];
+const NPC_GROUP: &str = "npcs";
+const TRIGGER_GROUP: &str = "triggers";
+const EXIT_GROUP: &str = "exits";
+const SHORT_EXIT: &str = "short_exit";
+const LONG_EXIT: &str = "long_exit";
+
+// Tiled stores flip flags in the top bits of each gid.
+const GID_MASK: u32 = 0x1fff_ffff;
+
+fn first_gid(layer: usize) -> usize {
+    1 + layer * TILESET_TILES
+}
+
+pub fn tileset_image_name(prefix: &str, layer: usize) -> String {
+    format!("{}_{}_tiles.png", prefix, LAYER_NAMES[layer])
+}
+
+struct Writer {
+    text: String,
+    next_id: usize,
+}
+
+impl Writer {
+    fn property(&mut self, name: &str, value: u32) {
+        self.text.push_str(&format!(
+            "      <property name=\"{}\" type=\"int\" value=\"{}\"/>\n",
+            name, value
+        ));
+    }
+
+    fn bool_property(&mut self, name: &str, value: bool) {
+        self.text.push_str(&format!(
+            "      <property name=\"{}\" type=\"bool\" value=\"{}\"/>\n",
+            name, value
+        ));
+    }
+
+    fn start_object(&mut self, name: &str, kind: &str, x: u8, y: u8) {
+        self.start_sized_object(name, kind, x, y, 1, 1);
+    }
+
+    // `w` and `h` are in tiles.
+    fn start_sized_object(&mut self, name: &str, kind: &str, x: u8, y: u8, w: usize, h: usize) {
+        self.text.push_str(&format!(
+            "  <object id=\"{}\" name=\"{}\" type=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\n    <properties>\n",
+            self.next_id,
+            name,
+            kind,
+            x as usize * TILE_SIZE,
+            y as usize * TILE_SIZE,
+            w * TILE_SIZE,
+            h * TILE_SIZE
+        ));
+        self.next_id += 1;
+    }
+
+    fn end_object(&mut self) {
+        self.text.push_str("    </properties>\n  </object>\n");
+    }
+
+    fn npc(&mut self, i: usize, npc: &Npc) {
+        self.start_object(&format!("npc {}", i), "npc", npc.x, npc.y);
+        self.property("event_addr", npc.event_addr);
+        self.property("palette", npc.palette as u32);
+        self.bool_property("solid_action_path", npc.solid_action_path);
+        self.property("enable_bit", npc.enable_bit as u32);
+        self.property("enable_addr", npc.enable_addr as u32);
+        self.bool_property("show_rider_in_vehicle", npc.show_rider_in_vehicle);
+        self.property("speed", npc.speed as u32);
+        self.property("sprite", npc.sprite as u32);
+        self.property("movement_type", npc.movement_type as u32);
+        self.property("map_layer", npc.map_layer as u32);
+        self.property("vehicle", npc.vehicle as u32);
+        self.property("start_direction", npc.start_direction as u32);
+        self.bool_property("turn_when_triggered", npc.turn_when_triggered);
+        self.property("unknown_8_bits", npc.unknown_8_bits as u32);
+        self.end_object();
+    }
+
+    fn trigger(&mut self, i: usize, trigger: &Trigger) {
+        self.start_object(&format!("trigger {}", i), "trigger", trigger.x, trigger.y);
+        self.property("event_addr", trigger.event_addr);
+        self.end_object();
+    }
+
+    fn destination(&mut self, dest: &Destination) {
+        self.property("map", dest.map as u32);
+        self.bool_property("set_parent_map", dest.set_parent_map);
+        self.bool_property("z_level", dest.z_level);
+        self.bool_property("show_name", dest.show_name);
+        self.property("facing", dest.facing as u32);
+        self.property("unknown_2_bits", dest.unknown_2_bits as u32);
+        self.property("dest_x", dest.x as u32);
+        self.property("dest_y", dest.y as u32);
+    }
+
+    fn short_exit(&mut self, i: usize, exit: &ShortExit) {
+        self.start_object(&format!("exit {}", i), SHORT_EXIT, exit.x, exit.y);
+        self.destination(&exit.dest);
+        self.end_object();
+    }
+
+    // Long exits are sized to the strip of tiles they cover.
+    fn long_exit(&mut self, i: usize, exit: &LongExit) {
+        let len = exit.length as usize + 1;
+        let (w, h) = if exit.vertical { (1, len) } else { (len, 1) };
+        self.start_sized_object(&format!("exit {}", i), LONG_EXIT, exit.x, exit.y, w, h);
+        self.property("length", exit.length as u32);
+        self.bool_property("vertical", exit.vertical);
+        self.destination(&exit.dest);
+        self.end_object();
+    }
+}
+
+// Exports a location as a Tiled map.  Each layer gets its own tileset
+// referencing an image named by tileset_image_name().
+pub fn export(location: &Location, tilemaps: &[Option<Tilemap>], prefix: &str) -> String {
+    let width = tilemaps
+        .iter()
+        .flatten()
+        .map(|t| t.width)
+        .max()
+        .unwrap_or(0);
+    let height = tilemaps
+        .iter()
+        .flatten()
+        .map(|t| t.height)
+        .max()
+        .unwrap_or(0);
+    let num_layers = tilemaps.iter().flatten().count();
+    let objects = location.npcs.len()
+        + location.triggers.len()
+        + location.short_exits.len()
+        + location.long_exits.len();
+
+    let mut w = Writer {
+        text: String::new(),
+        next_id: 1,
+    };
+    w.text
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    w.text.push_str(&format!(
+        "<map version=\"1.2\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"{}\" nextobjectid=\"{}\">\n",
+        width,
+        height,
+        TILE_SIZE,
+        TILE_SIZE,
+        num_layers + 4,
+        objects + 1
+    ));
+    w.text.push_str(&format!(
+        " <properties>\n  <property name=\"entrance_event_addr\" type=\"int\" value=\"{}\"/>\n </properties>\n",
+        location.entrance_event_addr
+    ));
+
+    for (layer, tilemap) in tilemaps.iter().enumerate() {
+        if tilemap.is_none() {
+            continue;
+        }
+        let size = TILESET_COLUMNS * TILE_SIZE;
+        w.text.push_str(&format!(
+            " <tileset firstgid=\"{}\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" tilecount=\"{}\" columns=\"{}\">\n  <image source=\"{}\" width=\"{}\" height=\"{}\"/>\n </tileset>\n",
+            first_gid(layer),
+            LAYER_NAMES[layer],
+            TILE_SIZE,
+            TILE_SIZE,
+            TILESET_TILES,
+            TILESET_COLUMNS,
+            tileset_image_name(prefix, layer),
+            size,
+            size
+        ));
+    }
+
+    // Layers are padded to the map size with empty tiles.
+    let mut layer_id = 1;
+    for (layer, tilemap) in tilemaps.iter().enumerate() {
+        let tilemap = match tilemap {
+            Some(t) => t,
+            None => continue,
+        };
+        w.text.push_str(&format!(
+            " <layer id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\">\n  <data encoding=\"csv\">\n",
+            layer_id, LAYER_NAMES[layer], width, height
+        ));
+        layer_id += 1;
+        let mut rows = Vec::new();
+        for y in 0..height {
+            let row: Vec<String> = (0..width)
+                .map(|x| {
+                    if x < tilemap.width && y < tilemap.height {
+                        let t = tilemap.tiles[y * tilemap.width + x] as usize;
+                        (first_gid(layer) + t).to_string()
+                    } else {
+                        "0".to_string()
+                    }
+                })
+                .collect();
+            rows.push(row.join(","));
+        }
+        w.text.push_str(&rows.join(",\n"));
+        w.text.push_str("\n  </data>\n </layer>\n");
+    }
+
+    w.text.push_str(&format!(
+        " <objectgroup id=\"{}\" name=\"{}\">\n",
+        layer_id, NPC_GROUP
+    ));
+    for (i, npc) in location.npcs.iter().enumerate() {
+        w.npc(i, npc);
+    }
+    w.text.push_str(" </objectgroup>\n");
+    w.text.push_str(&format!(
+        " <objectgroup id=\"{}\" name=\"{}\">\n",
+        layer_id + 1,
+        TRIGGER_GROUP
+    ));
+    for (i, trigger) in location.triggers.iter().enumerate() {
+        w.trigger(i, trigger);
+    }
+    w.text.push_str(" </objectgroup>\n");
+    // Short exits are numbered first, followed by the long exits.
+    w.text.push_str(&format!(
+        " <objectgroup id=\"{}\" name=\"{}\">\n",
+        layer_id + 2,
+        EXIT_GROUP
+    ));
+    for (i, exit) in location.short_exits.iter().enumerate() {
+        w.short_exit(i, exit);
+    }
+    for (i, exit) in location.long_exits.iter().enumerate() {
+        w.long_exit(location.short_exits.len() + i, exit);
+    }
+    w.text.push_str(" </objectgroup>\n</map>\n");
+    w.text
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Import {
+    pub location: Location,
+    pub tilemaps: Vec<Option<Tilemap>>,
+}
+
+struct Object {
+    kind: String,
+    x: usize,
+    y: usize,
+    properties: HashMap<String, String>,
+}
+
+impl Object {
+    fn pos(&self) -> Result<(u8, u8), Box<Error>> {
+        let (x, y) = (self.x / TILE_SIZE, self.y / TILE_SIZE);
+        if x > 0xff || y > 0xff {
+            return Err(
+                SimpleError::new(format!("object position ({}, {}) out of range", x, y)).into(),
+            );
+        }
+        Ok((x as u8, y as u8))
+    }
+
+    fn int(&self, name: &str) -> Result<u32, Box<Error>> {
+        match self.properties.get(name) {
+            Some(v) => Ok(v.parse()?),
+            None => Err(SimpleError::new(format!("object missing property {}", name)).into()),
+        }
+    }
+
+    fn ranged(&self, name: &str, max: u32) -> Result<u32, Box<Error>> {
+        let v = self.int(name)?;
+        if v > max {
+            return Err(SimpleError::new(format!(
+                "object property {} value {} greater than {}",
+                name, v, max
+            ))
+            .into());
+        }
+        Ok(v)
+    }
+
+    fn byte(&self, name: &str) -> Result<u8, Box<Error>> {
+        Ok(self.ranged(name, 0xff)? as u8)
+    }
+
+    fn bool(&self, name: &str) -> Result<bool, Box<Error>> {
+        match self.properties.get(name) {
+            Some(v) => Ok(v.parse()?),
+            None => Err(SimpleError::new(format!("object missing property {}", name)).into()),
+        }
+    }
+
+    fn npc(&self) -> Result<Npc, Box<Error>> {
+        let (x, y) = self.pos()?;
+        Ok(Npc {
+            event_addr: self.int("event_addr")?,
+            palette: self.byte("palette")?,
+            solid_action_path: self.bool("solid_action_path")?,
+            enable_bit: self.byte("enable_bit")?,
+            enable_addr: self.byte("enable_addr")?,
+            x: x,
+            show_rider_in_vehicle: self.bool("show_rider_in_vehicle")?,
+            y: y,
+            speed: self.byte("speed")?,
+            sprite: self.byte("sprite")?,
+            movement_type: self.byte("movement_type")?,
+            map_layer: self.byte("map_layer")?,
+            vehicle: self.byte("vehicle")?,
+            start_direction: self.byte("start_direction")?,
+            turn_when_triggered: self.bool("turn_when_triggered")?,
+            unknown_8_bits: self.byte("unknown_8_bits")?,
+        })
+    }
+
+    fn trigger(&self) -> Result<Trigger, Box<Error>> {
+        let (x, y) = self.pos()?;
+        Ok(Trigger {
+            x: x,
+            y: y,
+            event_addr: self.int("event_addr")?,
+        })
+    }
+
+    fn destination(&self) -> Result<Destination, Box<Error>> {
+        Ok(Destination {
+            map: self.ranged("map", 0x1ff)? as u16,
+            set_parent_map: self.bool("set_parent_map")?,
+            z_level: self.bool("z_level")?,
+            show_name: self.bool("show_name")?,
+            facing: self.byte("facing")?,
+            unknown_2_bits: self.byte("unknown_2_bits")?,
+            x: self.byte("dest_x")?,
+            y: self.byte("dest_y")?,
+        })
+    }
+
+    fn short_exit(&self) -> Result<ShortExit, Box<Error>> {
+        let (x, y) = self.pos()?;
+        Ok(ShortExit {
+            x: x,
+            y: y,
+            dest: self.destination()?,
+        })
+    }
+
+    fn long_exit(&self) -> Result<LongExit, Box<Error>> {
+        let (x, y) = self.pos()?;
+        Ok(LongExit {
+            x: x,
+            y: y,
+            length: self.byte("length")?,
+            vertical: self.bool("vertical")?,
+            dest: self.destination()?,
+        })
+    }
+}
+
+fn attr(attributes: &[::xml::attribute::OwnedAttribute], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|a| a.name.local_name == name)
+        .map(|a| a.value.clone())
+}
+
+fn parse_usize(
+    attributes: &[::xml::attribute::OwnedAttribute],
+    name: &str,
+) -> Result<usize, Box<Error>> {
+    match attr(attributes, name) {
+        // Tiled may write fractional object positions.
+        Some(v) => Ok(v.parse::<f64>()? as usize),
+        None => Err(SimpleError::new(format!("missing attribute {}", name)).into()),
+    }
+}
+
+// Reads a Tiled map exported by export() back into a location.  Properties
+// not represented in the map, such as treasures, are taken from `base`.  Layers are cropped to
+// the layer sizes given by base's properties.
+pub fn import(text: &str, base: &Location) -> Result<Import, Box<Error>> {
+    let mut first_gids: HashMap<String, usize> = HashMap::new();
+    let mut layers: HashMap<String, (usize, Vec<u32>)> = HashMap::new();
+    let mut groups: HashMap<String, Vec<Object>> = HashMap::new();
+    let mut map_properties: HashMap<String, String> = HashMap::new();
+
+    let mut layer: Option<(String, usize)> = None;
+    let mut group: Option<String> = None;
+    let mut object: Option<Object> = None;
+    let mut data = String::new();
+
+    for e in EventReader::from_str(text) {
+        match e? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => match name.local_name.as_str() {
+                "tileset" => {
+                    let n = attr(&attributes, "name").unwrap_or_default();
+                    first_gids.insert(n, parse_usize(&attributes, "firstgid")?);
+                }
+                "layer" => {
+                    let n = attr(&attributes, "name").unwrap_or_default();
+                    layer = Some((n, parse_usize(&attributes, "width")?));
+                    data.clear();
+                }
+                "data" if attr(&attributes, "encoding").as_deref() != Some("csv") => {
+                    return Err(SimpleError::new("only csv layer data is supported").into());
+                }
+                "objectgroup" => group = attr(&attributes, "name"),
+                "object" => {
+                    object = Some(Object {
+                        // Tiled 1.9 renamed the object "type" attribute to "class".
+                        kind: attr(&attributes, "class")
+                            .or_else(|| attr(&attributes, "type"))
+                            .unwrap_or_default(),
+                        x: parse_usize(&attributes, "x")?,
+                        y: parse_usize(&attributes, "y")?,
+                        properties: HashMap::new(),
+                    })
+                }
+                "property" => {
+                    let n = attr(&attributes, "name").unwrap_or_default();
+                    let v = attr(&attributes, "value").unwrap_or_default();
+                    match object {
+                        Some(ref mut o) => o.properties.insert(n, v),
+                        None => map_properties.insert(n, v),
+                    };
+                }
+                _ => (),
+            },
+            XmlEvent::Characters(s) if layer.is_some() => data.push_str(&s),
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "layer" => {
+                    if let Some((n, width)) = layer.take() {
+                        let mut gids = Vec::new();
+                        for v in data.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                            gids.push(v.parse::<u32>()? & GID_MASK);
+                        }
+                        layers.insert(n, (width, gids));
+                    }
+                }
+                "object" => {
+                    if let (Some(o), Some(g)) = (object.take(), group.as_ref()) {
+                        groups.entry(g.clone()).or_default().push(o);
+                    }
+                }
+                "objectgroup" => group = None,
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+
+    let props = &base.properties;
+    let dims = [
+        (&props.bg1_w, &props.bg1_h),
+        (&props.bg2_w, &props.bg2_h),
+        (&props.bg3_w, &props.bg3_h),
+    ];
+    let mut tilemaps = Vec::new();
+    for (i, (w, h)) in dims.iter().enumerate() {
+        let name = LAYER_NAMES[i];
+        let (map_width, gids) = match layers.get(name) {
+            Some(l) => l,
+            None => {
+                tilemaps.push(None);
+                continue;
+            }
+        };
+        let first_gid = first_gids.get(name).cloned().unwrap_or(first_gid(i));
+        let (width, height) = (bg_tiles(w), bg_tiles(h));
+        let mut tiles = vec![0u8; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let gid = gids.get(y * map_width + x).cloned().unwrap_or(0) as usize;
+                if gid >= first_gid + TILESET_TILES {
+                    return Err(SimpleError::new(format!(
+                        "{} tile ({}, {}) uses gid {} from another tileset",
+                        name, x, y, gid
+                    ))
+                    .into());
+                }
+                if gid >= first_gid {
+                    tiles[y * width + x] = (gid - first_gid) as u8;
+                }
+            }
+        }
+        tilemaps.push(Some(Tilemap {
+            width: width,
+            height: height,
+            tiles: tiles,
+        }));
+    }
+
+    let mut location = base.clone();
+    if let Some(v) = map_properties.get("entrance_event_addr") {
+        location.entrance_event_addr = v.parse()?;
+    }
+    location.npcs = Vec::new();
+    for o in groups.get(NPC_GROUP).into_iter().flatten() {
+        location.npcs.push(o.npc()?);
+    }
+    location.triggers = Vec::new();
+    for o in groups.get(TRIGGER_GROUP).into_iter().flatten() {
+        location.triggers.push(o.trigger()?);
+    }
+    location.short_exits = Vec::new();
+    location.long_exits = Vec::new();
+    for o in groups.get(EXIT_GROUP).into_iter().flatten() {
+        match o.kind.as_str() {
+            SHORT_EXIT => location.short_exits.push(o.short_exit()?),
+            LONG_EXIT => location.long_exits.push(o.long_exit()?),
+            k => return Err(SimpleError::new(format!("unknown exit type \"{}\"", k)).into()),
+        }
+    }
+
+    Ok(Import {
+        location: location,
+        tilemaps: tilemaps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use field::exit::parse_destination;
+    use field::npc::parse_npcs;
+    use field::properties;
+
+    #[test]
+    fn round_trip_test() {
+        let npcs = [
+            0x77, 0xd2, 0x06, 0xc0, 0x40, 0x47, 0x36, 0x00, 0x02, 0xa7, 0xd2, 0x06, 0xc0, 0x08,
+            0x66, 0x36, 0x00, 0x02,
+        ];
+        let location = Location {
+            properties: properties::test_properties(),
+            entrance_event_addr: 0x1234,
+            triggers: vec![Trigger {
+                x: 8,
+                y: 46,
+                event_addr: 145663,
+            }],
+            npcs: parse_npcs(&npcs).unwrap(),
+            short_exits: vec![ShortExit {
+                x: 3,
+                y: 4,
+                dest: parse_destination(&[0xff, 0x01, 0x20, 0x30]),
+            }],
+            long_exits: vec![LongExit {
+                x: 5,
+                y: 6,
+                length: 2,
+                vertical: true,
+                dest: parse_destination(&[0x13, 0x9a, 0x07, 0x0c]),
+            }],
+            treasures: Vec::new(),
+        };
+        let tilemaps = vec![
+            Some(Tilemap {
+                width: 16,
+                height: 16,
+                tiles: (0..0x100).map(|t| t as u8).collect(),
+            }),
+            None,
+            None,
+        ];
+
+        let text = export(&location, &tilemaps, "000");
+        let import = import(&text, &location).unwrap();
+        assert_eq!(location, import.location);
+        assert_eq!(tilemaps, import.tilemaps);
+
+        // Tiled 1.9 and later write "class" in place of "type".
+        let class_text = text.replace(" type=\"short_exit\"", " class=\"short_exit\"");
+        assert_eq!(
+            location,
+            super::import(&class_text, &location).unwrap().location
+        );
+
+        let bad_sprite = text.replace(
+            "name=\"sprite\" type=\"int\" value=\"54\"",
+            "name=\"sprite\" type=\"int\" value=\"310\"",
+        );
+        assert!(super::import(&bad_sprite, &location).is_err());
+        let bad_x = text.replace(" x=\"48\" y=\"64\"", " x=\"4096\" y=\"64\"");
+        assert!(super::import(&bad_x, &location).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use ptr_table;
+use rom_map;
+use utils::check_len;
+
+const TREASURE_SIZE: usize = 5;
+const NUM_ENTRIES: usize = 0x1a0;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Empty,
+    // Amount is the formation pack fought through the chest's
+    // InvokeBattleOnChestOpen event.
+    Monster,
+    // Amount is the item index.
+    Item,
+    // Amount is in units of 100 GP.
+    Gold,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Treasure {
+    pub x: u8,
+    pub y: u8,
+    // Bit in the treasure flags at $1E40 set once the chest is opened.
+    pub opened_bit: u16,
+    pub unknown_4_bits: u8,
+    pub kind: Kind,
+    pub amount: u8,
+}
+
+impl Treasure {
+    pub fn gold(&self) -> Option<u32> {
+        match self.kind {
+            Kind::Gold => Some(self.amount as u32 * 100),
+            _ => None,
+        }
+    }
+}
+
+pub fn ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::TREASURE_POINTERS);
+    check_len(rom_data, addr, NUM_ENTRIES * 2)?;
+    Ok(ptr_table::Table::new(
+        &rom_data[addr..],
+        NUM_ENTRIES,
+        rom_map::snes_to_file(rom_map::TREASURE_DATA),
+    ))
+}
+
+pub fn parse_treasure(data: &[u8]) -> Result<Treasure, Box<Error>> {
+    if data.len() < TREASURE_SIZE {
+        return Err(SimpleError::new("data does not contain at least 0x5 bytes").into());
+    }
+
+    let kind = if data[3] & 0x80 != 0 {
+        Kind::Gold
+    } else if data[3] & 0x40 != 0 {
+        Kind::Item
+    } else if data[3] & 0x20 != 0 {
+        Kind::Monster
+    } else {
+        Kind::Empty
+    };
+    Ok(Treasure {
+        x: data[0],
+        y: data[1],
+        opened_bit: data[2] as u16 | (data[3] as u16 & 0x1) << 8,
+        unknown_4_bits: (data[3] >> 1) & 0xf,
+        kind: kind,
+        amount: data[4],
+    })
+}
+
+pub fn parse_treasures(data: &[u8]) -> Result<Vec<Treasure>, Box<Error>> {
+    if !data.len().is_multiple_of(TREASURE_SIZE) {
+        return Err(SimpleError::new(format!(
+            "treasure data length {} not a multiple of {}",
+            data.len(),
+            TREASURE_SIZE
+        ))
+        .into());
+    }
+    data.chunks(TREASURE_SIZE).map(parse_treasure).collect()
+}
+
+pub fn encode_treasures(treasures: &[Treasure]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for t in treasures {
+        let kind = match t.kind {
+            Kind::Empty => 0x00,
+            Kind::Monster => 0x20,
+            Kind::Item => 0x40,
+            Kind::Gold => 0x80,
+        };
+        data.push(t.x);
+        data.push(t.y);
+        data.push(t.opened_bit as u8);
+        data.push((t.opened_bit >> 8) as u8 & 0x1 | (t.unknown_4_bits & 0xf) << 1 | kind);
+        data.push(t.amount);
+    }
+    data
+}
+
+// Writes every location's treasure back to the ROM.
+pub fn write_treasures(rom_data: &mut [u8], treasures: &[Vec<Treasure>]) -> Result<(), Box<Error>> {
+    if treasures.len() != NUM_ENTRIES - 1 {
+        return Err(SimpleError::new(format!(
+            "expected {} treasure lists.  Got {}.",
+            NUM_ENTRIES - 1,
+            treasures.len()
+        ))
+        .into());
+    }
+    let entries: Vec<Vec<u8>> = treasures.iter().map(|t| encode_treasures(t)).collect();
+    ptr_table::write_u16(
+        rom_data,
+        rom_map::snes_to_file(rom_map::TREASURE_POINTERS),
+        rom_map::snes_to_file(rom_map::TREASURE_DATA),
+        &entries,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_test() {
+        let data = [
+            0x2e, 0x0c, 0x05, 0x40, 0xe8, 0x10, 0x14, 0x2a, 0x81, 0x0f, 0x04, 0x07, 0x00, 0x20,
+            0x1b,
+        ];
+        let treasures = vec![
+            Treasure {
+                x: 0x2e,
+                y: 0x0c,
+                opened_bit: 0x005,
+                unknown_4_bits: 0,
+                kind: Kind::Item,
+                amount: 0xe8,
+            },
+            Treasure {
+                x: 0x10,
+                y: 0x14,
+                opened_bit: 0x12a,
+                unknown_4_bits: 0,
+                kind: Kind::Gold,
+                amount: 0x0f,
+            },
+            Treasure {
+                x: 0x04,
+                y: 0x07,
+                opened_bit: 0x000,
+                unknown_4_bits: 0,
+                kind: Kind::Monster,
+                amount: 0x1b,
+            },
+        ];
+
+        assert_eq!(treasures, parse_treasures(&data).unwrap());
+        assert_eq!(data.to_vec(), encode_treasures(&treasures));
+        assert_eq!(Some(1500), treasures[1].gold());
+        assert_eq!(None, treasures[0].gold());
+        assert!(parse_treasures(&data[..7]).is_err());
+    }
+}
@@ -5,11 +5,14 @@ use ptr_table;
 use rom_map;
 use utils::get_u24;
 
+const TRIGGER_SIZE: usize = 5;
+const NUM_ENTRIES: usize = 0x1a0;
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Trigger {
-    x: u8,
-    y: u8,
-    event_addr: u32,
+    pub x: u8,
+    pub y: u8,
+    pub event_addr: u32,
 }
 
 pub fn ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
@@ -17,14 +20,14 @@ pub fn ptr_table(rom_data: &[u8]) -> Result<ptr_table::Table, Box<Error>> {
     let addr = rom_map::snes_to_file(rom_map::EVENT_TRIGGER_POINTERS);
     let table = ptr_table::Table::new(
         &rom_data[addr..],
-        0x1a0,
+        NUM_ENTRIES,
         rom_map::snes_to_file(rom_map::EVENT_TRIGGER_POINTERS),
     );
     Ok(table)
 }
 
 pub fn parse_trigger(data: &[u8]) -> Result<Trigger, Box<Error>> {
-    if data.len() < 0x5 {
+    if data.len() < TRIGGER_SIZE {
         return Err(SimpleError::new("data does not contain at least 0x5 bytes").into());
     }
 
@@ -36,14 +39,41 @@ pub fn parse_trigger(data: &[u8]) -> Result<Trigger, Box<Error>> {
 }
 
 pub fn parse_triggers(data: &[u8]) -> Result<Vec<Trigger>, Box<Error>> {
-    let num = data.len() / 5;
+    let num = data.len() / TRIGGER_SIZE;
     let mut npcs = Vec::new();
     for i in 0..num {
-        npcs.push(parse_trigger(&data[(i * 5)..])?);
+        npcs.push(parse_trigger(&data[(i * TRIGGER_SIZE)..])?);
     }
     Ok(npcs)
 }
 
+pub fn encode_triggers(triggers: &[Trigger]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for t in triggers {
+        data.push(t.x);
+        data.push(t.y);
+        data.push(t.event_addr as u8);
+        data.push((t.event_addr >> 8) as u8);
+        data.push((t.event_addr >> 16) as u8);
+    }
+    data
+}
+
+// Writes every location's event triggers back to the ROM.
+pub fn write_triggers(rom_data: &mut [u8], triggers: &[Vec<Trigger>]) -> Result<(), Box<Error>> {
+    if triggers.len() != NUM_ENTRIES - 1 {
+        return Err(SimpleError::new(format!(
+            "expected {} trigger lists.  Got {}.",
+            NUM_ENTRIES - 1,
+            triggers.len()
+        ))
+        .into());
+    }
+    let entries: Vec<Vec<u8>> = triggers.iter().map(|t| encode_triggers(t)).collect();
+    let addr = rom_map::snes_to_file(rom_map::EVENT_TRIGGER_POINTERS);
+    ptr_table::write_u16(rom_data, addr, addr, &entries)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -65,5 +95,6 @@ mod tests {
         ];
 
         assert_eq!(triggers, parse_triggers(&data).unwrap());
+        assert_eq!(data.to_vec(), encode_triggers(&triggers));
     }
 }
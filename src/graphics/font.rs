@@ -0,0 +1,257 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use graphics::tile::{self, Format, Sheet, Tile};
+use graphics::{Color, Image};
+use rom_map;
+use utils::check_len;
+
+// Dialog glyphs are 16x11 pixels at 1bpp, stored as one 16 bit row each with
+// the leftmost pixel in the msb.
+pub const GLYPH_WIDTH: usize = 16;
+pub const GLYPH_HEIGHT: usize = 11;
+const GLYPH_LEN: usize = GLYPH_HEIGHT * 2;
+// The width table has an entry for every character code but only codes 0x80
+// and up have dialog glyphs.
+const WIDTHS_LEN: usize = 0x100;
+const FIRST_DIALOG_CHAR: usize = 0x80;
+
+const SHEET_COLUMNS: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Glyph {
+    // Advance in pixels used by the variable width renderer.
+    pub width: u8,
+    pub rows: [u16; GLYPH_HEIGHT],
+}
+
+impl Glyph {
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.rows[y] & (0x8000 >> x) != 0
+    }
+}
+
+// Small font index 1 is the text color and 2 and 3 are shading.  The dialog
+// font only uses index 1.
+pub fn palette() -> Vec<Color> {
+    [0x00, 0xff, 0xa0, 0x50]
+        .iter()
+        .map(|v| Color {
+            r: *v,
+            g: *v,
+            b: *v,
+            a: 0xff,
+        })
+        .collect()
+}
+
+fn table(rom_data: &[u8], snes_addr: usize, len: usize) -> Result<usize, Box<Error>> {
+    let addr = rom_map::snes_to_file(snes_addr);
+    check_len(rom_data, addr, len)?;
+    Ok(addr)
+}
+
+fn check_count(what: &str, expected: usize, got: usize) -> Result<(), Box<Error>> {
+    if expected != got {
+        return Err(
+            SimpleError::new(format!("expected {} {}.  Got {}.", expected, what, got)).into(),
+        );
+    }
+    Ok(())
+}
+
+pub fn parse_small(rom_data: &[u8]) -> Result<Vec<Tile>, Box<Error>> {
+    let len = rom_map::NUM_SMALL_FONT_GLYPHS * Format::Bpp2.tile_len();
+    let addr = table(rom_data, rom_map::SMALL_FONT_GRAPHICS, len)?;
+    Ok(tile::decode(&rom_data[addr..addr + len], Format::Bpp2))
+}
+
+pub fn write_small(rom_data: &mut [u8], glyphs: &[Tile]) -> Result<(), Box<Error>> {
+    check_count(
+        "small font glyphs",
+        rom_map::NUM_SMALL_FONT_GLYPHS,
+        glyphs.len(),
+    )?;
+    let data = tile::encode(glyphs, Format::Bpp2);
+    let addr = table(rom_data, rom_map::SMALL_FONT_GRAPHICS, data.len())?;
+    rom_data[addr..addr + data.len()].copy_from_slice(&data);
+    Ok(())
+}
+
+pub fn decode_glyph(data: &[u8], width: u8) -> Glyph {
+    let mut rows = [0u16; GLYPH_HEIGHT];
+    for (i, r) in data.chunks(2).take(GLYPH_HEIGHT).enumerate() {
+        rows[i] = (r[0] as u16) << 8 | r.get(1).cloned().unwrap_or(0) as u16;
+    }
+    Glyph {
+        width: width,
+        rows: rows,
+    }
+}
+
+pub fn encode_glyph(glyph: &Glyph) -> Vec<u8> {
+    let mut data = Vec::new();
+    for r in glyph.rows.iter() {
+        data.push((r >> 8) as u8);
+        data.push(*r as u8);
+    }
+    data
+}
+
+pub fn parse_dialog(rom_data: &[u8]) -> Result<Vec<Glyph>, Box<Error>> {
+    let n = rom_map::NUM_DIALOG_FONT_GLYPHS;
+    let widths = table(rom_data, rom_map::DIALOG_FONT_WIDTHS, WIDTHS_LEN)? + FIRST_DIALOG_CHAR;
+    let addr = table(rom_data, rom_map::DIALOG_FONT_GRAPHICS, n * GLYPH_LEN)?;
+    Ok((0..n)
+        .map(|i| decode_glyph(&rom_data[addr + i * GLYPH_LEN..], rom_data[widths + i]))
+        .collect())
+}
+
+pub fn write_dialog(rom_data: &mut [u8], glyphs: &[Glyph]) -> Result<(), Box<Error>> {
+    let n = rom_map::NUM_DIALOG_FONT_GLYPHS;
+    check_count("dialog font glyphs", n, glyphs.len())?;
+    let widths = table(rom_data, rom_map::DIALOG_FONT_WIDTHS, WIDTHS_LEN)? + FIRST_DIALOG_CHAR;
+    let addr = table(rom_data, rom_map::DIALOG_FONT_GRAPHICS, n * GLYPH_LEN)?;
+    for (i, g) in glyphs.iter().enumerate() {
+        if g.width as usize > GLYPH_WIDTH {
+            return Err(SimpleError::new(format!(
+                "dialog glyph {:02x} is {} pixels wide.  Max is {}.",
+                i, g.width, GLYPH_WIDTH
+            ))
+            .into());
+        }
+        rom_data[widths + i] = g.width;
+        let start = addr + i * GLYPH_LEN;
+        rom_data[start..start + GLYPH_LEN].copy_from_slice(&encode_glyph(g));
+    }
+    Ok(())
+}
+
+pub fn small_sheet(glyphs: &[Tile]) -> Sheet {
+    Sheet::from_tiles(glyphs, SHEET_COLUMNS)
+}
+
+pub fn small_from_image(image: &Image) -> Result<Vec<Tile>, Box<Error>> {
+    let mut glyphs = Sheet::from_image(image, &palette())?.to_tiles();
+    glyphs.truncate(rom_map::NUM_SMALL_FONT_GLYPHS);
+    Ok(glyphs)
+}
+
+// Glyphs are laid out in a grid of 16x11 cells.  Widths aren't part of the
+// sheet and are kept separately.
+pub fn dialog_sheet(glyphs: &[Glyph]) -> Sheet {
+    let rows = glyphs.len().div_ceil(SHEET_COLUMNS);
+    let width = SHEET_COLUMNS * GLYPH_WIDTH;
+    let mut sheet = Sheet {
+        width: width,
+        height: rows * GLYPH_HEIGHT,
+        pixels: vec![0u8; width * rows * GLYPH_HEIGHT],
+    };
+    for (i, g) in glyphs.iter().enumerate() {
+        let gx = (i % SHEET_COLUMNS) * GLYPH_WIDTH;
+        let gy = (i / SHEET_COLUMNS) * GLYPH_HEIGHT;
+        for y in 0..GLYPH_HEIGHT {
+            for x in 0..GLYPH_WIDTH {
+                if g.pixel(x, y) {
+                    sheet.pixels[(gy + y) * width + gx + x] = 1;
+                }
+            }
+        }
+    }
+    sheet
+}
+
+// Reads glyphs back from a sheet, taking their widths from `widths`.
+pub fn dialog_from_image(image: &Image, widths: &[u8]) -> Result<Vec<Glyph>, Box<Error>> {
+    let sheet = Sheet::from_image(image, &palette()[..2])?;
+    if sheet.width != SHEET_COLUMNS * GLYPH_WIDTH
+        || sheet.height / GLYPH_HEIGHT * SHEET_COLUMNS < widths.len()
+    {
+        return Err(SimpleError::new(format!(
+            "dialog font sheet is {}x{}.  Expected {} glyphs in {} columns.",
+            sheet.width,
+            sheet.height,
+            widths.len(),
+            SHEET_COLUMNS
+        ))
+        .into());
+    }
+    let mut glyphs = Vec::new();
+    for (i, w) in widths.iter().enumerate() {
+        let gx = (i % SHEET_COLUMNS) * GLYPH_WIDTH;
+        let gy = (i / SHEET_COLUMNS) * GLYPH_HEIGHT;
+        let mut rows = [0u16; GLYPH_HEIGHT];
+        for (y, r) in rows.iter_mut().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if sheet.pixels[(gy + y) * sheet.width + gx + x] != 0 {
+                    *r |= 0x8000 >> x;
+                }
+            }
+        }
+        glyphs.push(Glyph {
+            width: *w,
+            rows: rows,
+        });
+    }
+    Ok(glyphs)
+}
+
+// Dialog sheet with each glyph's advance marked by a red pixel on its bottom
+// row.  For viewing only.
+pub fn dialog_preview(glyphs: &[Glyph]) -> Image {
+    let mut image = dialog_sheet(glyphs).to_image(&palette());
+    let marker = Color {
+        r: 0xff,
+        g: 0x00,
+        b: 0x00,
+        a: 0xff,
+    };
+    for (i, g) in glyphs.iter().enumerate() {
+        let x = (i % SHEET_COLUMNS) * GLYPH_WIDTH + g.width as usize;
+        let y = (i / SHEET_COLUMNS) * GLYPH_HEIGHT;
+        if g.width > 0 && (g.width as usize) <= GLYPH_WIDTH {
+            image.set_pixel(x - 1, y + GLYPH_HEIGHT - 1, marker);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphics::tile::TILE_SIZE;
+
+    #[test]
+    fn dialog_test() {
+        let mut data = vec![0u8; GLYPH_LEN];
+        data[0] = 0x80;
+        data[21] = 0x01;
+        let g = decode_glyph(&data, 5);
+        assert!(g.pixel(0, 0));
+        assert!(g.pixel(15, 10));
+        assert!(!g.pixel(1, 0));
+        assert_eq!(data, encode_glyph(&g));
+
+        let glyphs = vec![g.clone(); 20];
+        let image = dialog_sheet(&glyphs).to_image(&palette());
+        assert_eq!((256, 22), (image.width, image.height));
+        let widths: Vec<u8> = glyphs.iter().map(|g| g.width).collect();
+        assert_eq!(glyphs, dialog_from_image(&image, &widths).unwrap());
+        assert!(dialog_from_image(&image, &[0u8; 40]).is_err());
+
+        let mut rom = vec![0u8; 0x400000];
+        rom[rom_map::snes_to_file(rom_map::DIALOG_FONT_WIDTHS) + 0x81] = 7;
+        let mut glyphs = parse_dialog(&rom).unwrap();
+        assert_eq!(7, glyphs[1].width);
+        glyphs[2].width = 3;
+        write_dialog(&mut rom, &glyphs).unwrap();
+        assert_eq!(
+            3,
+            rom[rom_map::snes_to_file(rom_map::DIALOG_FONT_WIDTHS) + 0x82]
+        );
+
+        let tiles = vec![[3u8; TILE_SIZE * TILE_SIZE]; 4];
+        let image = small_sheet(&tiles).to_image(&palette());
+        assert_eq!(tiles, small_from_image(&image).unwrap()[..4].to_vec());
+    }
+}
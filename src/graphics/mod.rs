@@ -0,0 +1,189 @@
+use png;
+use png::HasParameters;
+use simple_error::SimpleError;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+pub mod font;
+pub mod palette;
+pub mod tile;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub fn transparent() -> Color {
+        Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Color>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width: width,
+            height: height,
+            data: vec![Color::transparent(); width * height],
+        }
+    }
+
+    pub fn fill(&mut self, c: Color) {
+        for p in self.data.iter_mut() {
+            *p = c;
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Color {
+        self.data[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, c: Color) {
+        if x < self.width && y < self.height {
+            self.data[y * self.width + x] = c;
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, c: Color) {
+        for py in y..y + h {
+            for px in x..x + w {
+                self.set_pixel(px, py, c);
+            }
+        }
+    }
+
+    pub fn draw_rect(&mut self, x: usize, y: usize, w: usize, h: usize, c: Color) {
+        if w == 0 || h == 0 {
+            return;
+        }
+        for px in x..x + w {
+            self.set_pixel(px, y, c);
+            self.set_pixel(px, y + h - 1, c);
+        }
+        for py in y..y + h {
+            self.set_pixel(x, py, c);
+            self.set_pixel(x + w - 1, py, c);
+        }
+    }
+
+    // Draws `src` on top of this image skipping transparent pixels.
+    pub fn blit(&mut self, src: &Image, x: usize, y: usize) {
+        for sy in 0..src.height {
+            for sx in 0..src.width {
+                let c = src.get_pixel(sx, sy);
+                if c.a != 0 {
+                    self.set_pixel(x + sx, y + sy, c);
+                }
+            }
+        }
+    }
+
+    // Draws an 8x8 tile of palette indexes.  Index 0 is transparent.
+    pub fn draw_tile(
+        &mut self,
+        pixels: &tile::Tile,
+        palette: &[Color],
+        x: usize,
+        y: usize,
+        h_flip: bool,
+        v_flip: bool,
+    ) {
+        for ty in 0..8 {
+            for tx in 0..8 {
+                let sx = if h_flip { 7 - tx } else { tx };
+                let sy = if v_flip { 7 - ty } else { ty };
+                let p = pixels[sy * 8 + sx] as usize;
+                if p != 0 && p < palette.len() {
+                    self.set_pixel(x + tx, y + ty, palette[p]);
+                }
+            }
+        }
+    }
+
+    pub fn write_png(&self, path: &Path) -> Result<(), Box<Error>> {
+        let file = File::create(path)?;
+        let w = BufWriter::new(file);
+        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set(png::ColorType::RGBA).set(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        let mut data = Vec::with_capacity(self.data.len() * 4);
+        for c in &self.data {
+            data.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+        }
+        writer.write_image_data(&data)?;
+        Ok(())
+    }
+
+    pub fn read_png(path: &Path) -> Result<Image, Box<Error>> {
+        let decoder = png::Decoder::new(File::open(path)?);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf)?;
+
+        let pixel = |p: &[u8]| match info.color_type {
+            png::ColorType::RGBA => Some(Color {
+                r: p[0],
+                g: p[1],
+                b: p[2],
+                a: p[3],
+            }),
+            png::ColorType::RGB => Some(Color {
+                r: p[0],
+                g: p[1],
+                b: p[2],
+                a: 0xff,
+            }),
+            png::ColorType::GrayscaleAlpha => Some(Color {
+                r: p[0],
+                g: p[0],
+                b: p[0],
+                a: p[1],
+            }),
+            png::ColorType::Grayscale => Some(Color {
+                r: p[0],
+                g: p[0],
+                b: p[0],
+                a: 0xff,
+            }),
+            _ => None,
+        };
+        let bytes_per_pixel = info.color_type.samples();
+
+        let mut image = Image::new(info.width as usize, info.height as usize);
+        for (i, p) in buf
+            .chunks(bytes_per_pixel)
+            .take(image.data.len())
+            .enumerate()
+        {
+            image.data[i] = match pixel(p) {
+                Some(c) => c,
+                None => {
+                    return Err(SimpleError::new(format!(
+                        "unsupported png color type {:?}",
+                        info.color_type
+                    ))
+                    .into())
+                }
+            };
+        }
+        Ok(image)
+    }
+}
@@ -0,0 +1,291 @@
+use serde_json;
+use simple_error::SimpleError;
+use std::error::Error;
+
+use graphics::{Color, Image};
+use rom_map;
+use utils::check_len;
+
+const SWATCH_SIZE: usize = 16;
+const SWATCH_COLUMNS: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Map,
+    Sprite,
+    BattleBackground,
+    Monster,
+}
+
+impl Kind {
+    pub fn all() -> [Kind; 4] {
+        [
+            Kind::Map,
+            Kind::Sprite,
+            Kind::BattleBackground,
+            Kind::Monster,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Map => "map",
+            Kind::Sprite => "sprite",
+            Kind::BattleBackground => "battle_bg",
+            Kind::Monster => "monster",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Kind, Box<Error>> {
+        for kind in Kind::all().iter() {
+            if kind.name() == name {
+                return Ok(*kind);
+            }
+        }
+        Err(SimpleError::new(format!("Unknown palette kind {}", name)).into())
+    }
+
+    fn addr(&self) -> usize {
+        match self {
+            Kind::Map => rom_map::MAP_PALETTES,
+            Kind::Sprite => rom_map::SPRITE_PALETTES,
+            Kind::BattleBackground => rom_map::BATTLE_BG_PALETTES,
+            Kind::Monster => rom_map::MONSTER_PALETTES,
+        }
+    }
+
+    // Number of colors in each palette.  Monster palettes are indexed in
+    // units of 8 colors; 4bpp monsters use two consecutive palettes.
+    pub fn colors(&self) -> usize {
+        match self {
+            Kind::Map => 0x80,
+            Kind::Sprite => 0x10,
+            Kind::BattleBackground => 0x30,
+            Kind::Monster => 0x8,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        match self {
+            Kind::Map => rom_map::NUM_MAP_PALETTES,
+            Kind::Sprite => rom_map::NUM_SPRITE_PALETTES,
+            Kind::BattleBackground => rom_map::NUM_BATTLE_BG_PALETTES,
+            Kind::Monster => rom_map::NUM_MONSTER_PALETTES,
+        }
+    }
+
+    fn range(&self, index: usize) -> Result<(usize, usize), Box<Error>> {
+        self.span(index, 1)
+    }
+
+    // File range of `count` consecutive palettes starting at `index`.
+    fn span(&self, index: usize, count: usize) -> Result<(usize, usize), Box<Error>> {
+        if index + count > self.count() {
+            return Err(SimpleError::new(format!(
+                "{} palette index {} larger than 0x{:x}",
+                self.name(),
+                index,
+                self.count() - 1
+            ))
+            .into());
+        }
+        let len = self.colors() * 2;
+        let addr = rom_map::snes_to_file(self.addr()) + index * len;
+        Ok((addr, addr + len * count))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Palette {
+    pub colors: Vec<Color>,
+}
+
+// SNES colors are 15 bit little endian values with 5 bits each of blue,
+// green, and red from msb to lsb.
+pub fn decode_color(data: &[u8]) -> Color {
+    let v = data[0] as u16 | (data[1] as u16) << 8;
+    let expand = |c: u16| ((c << 3) | (c >> 2)) as u8;
+    Color {
+        r: expand(v & 0x1f),
+        g: expand((v >> 5) & 0x1f),
+        b: expand((v >> 10) & 0x1f),
+        a: 0xff,
+    }
+}
+
+pub fn encode_color(c: &Color) -> [u8; 2] {
+    let v = (c.r as u16 >> 3) | (c.g as u16 >> 3) << 5 | (c.b as u16 >> 3) << 10;
+    [(v & 0xff) as u8, (v >> 8) as u8]
+}
+
+pub fn decode(data: &[u8]) -> Palette {
+    Palette {
+        colors: data
+            .chunks(2)
+            .filter(|c| c.len() == 2)
+            .map(decode_color)
+            .collect(),
+    }
+}
+
+pub fn encode(palette: &Palette) -> Vec<u8> {
+    let mut data = Vec::with_capacity(palette.colors.len() * 2);
+    for c in &palette.colors {
+        data.extend_from_slice(&encode_color(c));
+    }
+    data
+}
+
+pub fn read(rom_data: &[u8], kind: Kind, index: usize) -> Result<Palette, Box<Error>> {
+    read_span(rom_data, kind, index, 1)
+}
+
+// Reads `count` consecutive palettes as one.
+pub fn read_span(
+    rom_data: &[u8],
+    kind: Kind,
+    index: usize,
+    count: usize,
+) -> Result<Palette, Box<Error>> {
+    let (start, end) = kind.span(index, count)?;
+    check_len(rom_data, start, end - start)?;
+    Ok(decode(&rom_data[start..end]))
+}
+
+pub fn write(
+    rom_data: &mut [u8],
+    kind: Kind,
+    index: usize,
+    palette: &Palette,
+) -> Result<(), Box<Error>> {
+    let (start, end) = kind.range(index)?;
+    if palette.colors.len() != kind.colors() {
+        return Err(SimpleError::new(format!(
+            "{} palettes have {} colors.  Palette has {}.",
+            kind.name(),
+            kind.colors(),
+            palette.colors.len()
+        ))
+        .into());
+    }
+    check_len(rom_data, start, end - start)?;
+    rom_data[start..end].copy_from_slice(&encode(palette));
+    Ok(())
+}
+
+impl Palette {
+    // Returns the `index`th group of `size` colors.  Map palettes, for
+    // instance, are eight 16 color palettes.
+    pub fn sub_palette(&self, index: usize, size: usize) -> &[Color] {
+        let start = (index * size).min(self.colors.len());
+        let end = (start + size).min(self.colors.len());
+        &self.colors[start..end]
+    }
+
+    pub fn to_swatch(&self) -> Image {
+        let rows = self.colors.len().div_ceil(SWATCH_COLUMNS);
+        let mut image = Image::new(SWATCH_COLUMNS * SWATCH_SIZE, rows * SWATCH_SIZE);
+        for (i, c) in self.colors.iter().enumerate() {
+            image.fill_rect(
+                (i % SWATCH_COLUMNS) * SWATCH_SIZE,
+                (i / SWATCH_COLUMNS) * SWATCH_SIZE,
+                SWATCH_SIZE,
+                SWATCH_SIZE,
+                *c,
+            );
+        }
+        image
+    }
+
+    pub fn to_gpl(&self, name: &str) -> String {
+        let mut text = format!(
+            "GIMP Palette\nName: {}\nColumns: {}\n#\n",
+            name, SWATCH_COLUMNS
+        );
+        for (i, c) in self.colors.iter().enumerate() {
+            text.push_str(&format!("{:3} {:3} {:3}\tIndex {}\n", c.r, c.g, c.b, i));
+        }
+        text
+    }
+
+    pub fn from_gpl(text: &str) -> Result<Palette, Box<Error>> {
+        let mut lines = text.lines();
+        if lines.next().map(|l| l.trim()) != Some("GIMP Palette") {
+            return Err(SimpleError::new("missing GIMP Palette header").into());
+        }
+
+        let mut colors = Vec::new();
+        for line in lines {
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("Name:")
+                || line.starts_with("Columns:")
+            {
+                continue;
+            }
+            let c: Vec<&str> = line.split_whitespace().take(3).collect();
+            if c.len() != 3 {
+                return Err(SimpleError::new(format!("bad palette line \"{}\"", line)).into());
+            }
+            colors.push(Color {
+                r: c[0].parse()?,
+                g: c[1].parse()?,
+                b: c[2].parse()?,
+                a: 0xff,
+            });
+        }
+        Ok(Palette { colors: colors })
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Palette, Box<Error>> {
+        Ok(serde_json::from_str(text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_test() {
+        let c = Color {
+            r: 0xff,
+            g: 0x00,
+            b: 0x84,
+            a: 0xff,
+        };
+        assert_eq!(c, decode_color(&[0x1f, 0x40]));
+        assert_eq!([0x1f, 0x40], encode_color(&c));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let data = [0x00, 0x00, 0xff, 0x7f, 0x1f, 0x40, 0x34, 0x12];
+        let palette = decode(&data);
+        assert_eq!(data.to_vec(), encode(&palette));
+        assert_eq!(palette, Palette::from_gpl(&palette.to_gpl("test")).unwrap());
+        assert_eq!(
+            palette,
+            Palette::from_json(&palette.to_json().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn rom_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let palette = decode(&[0x1f, 0x40].repeat(Kind::Sprite.colors()));
+        write(&mut rom, Kind::Sprite, 3, &palette).unwrap();
+        assert_eq!(palette, read(&rom, Kind::Sprite, 3).unwrap());
+        assert!(write(&mut rom, Kind::Map, 0, &palette).is_err());
+        assert!(read(&rom, Kind::Sprite, Kind::Sprite.count()).is_err());
+
+        let monster = read_span(&rom, Kind::Monster, 1, 2).unwrap();
+        assert_eq!(0x10, monster.colors.len());
+        assert!(read_span(&rom, Kind::Monster, Kind::Monster.count() - 1, 2).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use graphics::{Color, Image};
+
+pub const TILE_SIZE: usize = 8;
+
+// One palette index per pixel in row major order.
+pub type Tile = [u8; TILE_SIZE * TILE_SIZE];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    // SNES planar formats.  Bitplanes are stored in pairs with the two
+    // planes of each row interleaved.
+    Bpp2,
+    Bpp4,
+    // FF6 monster graphics: a 2bpp tile followed by the third bitplane with
+    // one byte per row.
+    Bpp3,
+}
+
+impl Format {
+    pub fn bpp(&self) -> usize {
+        match self {
+            Format::Bpp2 => 2,
+            Format::Bpp3 => 3,
+            Format::Bpp4 => 4,
+        }
+    }
+
+    // Size in bytes of one encoded tile.
+    pub fn tile_len(&self) -> usize {
+        self.bpp() * TILE_SIZE
+    }
+
+    fn plane_offset(&self, plane: usize, y: usize) -> usize {
+        match (self, plane) {
+            (Format::Bpp3, 2) => 16 + y,
+            _ => (plane / 2) * 16 + y * 2 + (plane & 0x1),
+        }
+    }
+}
+
+pub fn decode_tile(data: &[u8], format: Format) -> Tile {
+    let mut pixels = [0u8; TILE_SIZE * TILE_SIZE];
+    for y in 0..TILE_SIZE {
+        for plane in 0..format.bpp() {
+            let b = data[format.plane_offset(plane, y)];
+            for x in 0..TILE_SIZE {
+                pixels[y * TILE_SIZE + x] |= ((b >> (7 - x)) & 0x1) << plane;
+            }
+        }
+    }
+    pixels
+}
+
+pub fn encode_tile(tile: &Tile, format: Format) -> Vec<u8> {
+    let mut data = vec![0u8; format.tile_len()];
+    for y in 0..TILE_SIZE {
+        for plane in 0..format.bpp() {
+            let mut b = 0u8;
+            for x in 0..TILE_SIZE {
+                b |= ((tile[y * TILE_SIZE + x] >> plane) & 0x1) << (7 - x);
+            }
+            data[format.plane_offset(plane, y)] = b;
+        }
+    }
+    data
+}
+
+// Decodes every complete tile in data.
+pub fn decode(data: &[u8], format: Format) -> Vec<Tile> {
+    data.chunks(format.tile_len())
+        .filter(|c| c.len() == format.tile_len())
+        .map(|c| decode_tile(c, format))
+        .collect()
+}
+
+pub fn encode(tiles: &[Tile], format: Format) -> Vec<u8> {
+    let mut data = Vec::with_capacity(tiles.len() * format.tile_len());
+    for tile in tiles {
+        data.extend_from_slice(&encode_tile(tile, format));
+    }
+    data
+}
+
+// A sheet is an indexed image made of tiles laid out left to right, top to
+// bottom.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sheet {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Sheet {
+    pub fn from_tiles(tiles: &[Tile], columns: usize) -> Sheet {
+        let rows = tiles.len().div_ceil(columns);
+        let mut sheet = Sheet {
+            width: columns * TILE_SIZE,
+            height: rows * TILE_SIZE,
+            pixels: vec![0u8; columns * rows * TILE_SIZE * TILE_SIZE],
+        };
+        for (i, tile) in tiles.iter().enumerate() {
+            sheet.put_tile(tile, (i % columns) * TILE_SIZE, (i / columns) * TILE_SIZE);
+        }
+        sheet
+    }
+
+    pub fn put_tile(&mut self, tile: &Tile, x: usize, y: usize) {
+        for ty in 0..TILE_SIZE {
+            for tx in 0..TILE_SIZE {
+                if x + tx < self.width && y + ty < self.height {
+                    self.pixels[(y + ty) * self.width + x + tx] = tile[ty * TILE_SIZE + tx];
+                }
+            }
+        }
+    }
+
+    pub fn get_tile(&self, x: usize, y: usize) -> Tile {
+        let mut tile = [0u8; TILE_SIZE * TILE_SIZE];
+        for ty in 0..TILE_SIZE {
+            for tx in 0..TILE_SIZE {
+                if x + tx < self.width && y + ty < self.height {
+                    tile[ty * TILE_SIZE + tx] = self.pixels[(y + ty) * self.width + x + tx];
+                }
+            }
+        }
+        tile
+    }
+
+    pub fn to_tiles(&self) -> Vec<Tile> {
+        let mut tiles = Vec::new();
+        for y in 0..self.height / TILE_SIZE {
+            for x in 0..self.width / TILE_SIZE {
+                tiles.push(self.get_tile(x * TILE_SIZE, y * TILE_SIZE));
+            }
+        }
+        tiles
+    }
+
+    // Index 0 is drawn as transparent.
+    pub fn to_image(&self, palette: &[Color]) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for (i, p) in self.pixels.iter().enumerate() {
+            let p = *p as usize;
+            if p != 0 && p < palette.len() {
+                image.data[i] = palette[p];
+            }
+        }
+        image
+    }
+
+    // Converts an image back to palette indexes.  Every opaque pixel must
+    // exactly match a palette color.
+    pub fn from_image(image: &Image, palette: &[Color]) -> Result<Sheet, Box<Error>> {
+        let mut sheet = Sheet {
+            width: image.width,
+            height: image.height,
+            pixels: vec![0u8; image.width * image.height],
+        };
+        for (i, c) in image.data.iter().enumerate() {
+            if c.a == 0 {
+                continue;
+            }
+            let index = palette
+                .iter()
+                .skip(1)
+                .position(|p| p.r == c.r && p.g == c.g && p.b == c.b)
+                .map(|i| i + 1);
+            match index {
+                Some(index) => sheet.pixels[i] = index as u8,
+                None => {
+                    return Err(SimpleError::new(format!(
+                        "pixel ({}, {}) color {:?} not in palette",
+                        i % image.width,
+                        i / image.width,
+                        c
+                    ))
+                    .into())
+                }
+            }
+        }
+        Ok(sheet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i * 0x3b) ^ (i >> 2)) as u8).collect()
+    }
+
+    #[test]
+    fn decode_test() {
+        let data = [
+            0x80, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x01, 0x00, 0x00,
+        ];
+        let pixels = decode_tile(&data, Format::Bpp4);
+        assert_eq!(1, pixels[0]);
+        assert_eq!(2, pixels[8]);
+        assert_eq!(2, pixels[63]);
+        assert_eq!(8, pixels[55]);
+        assert_eq!(0, pixels[1]);
+
+        let mut data = [0u8; 24];
+        data[15] = 0x01;
+        data[23] = 0x80;
+        let pixels = decode_tile(&data, Format::Bpp3);
+        assert_eq!(2, pixels[63]);
+        assert_eq!(4, pixels[56]);
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for format in [Format::Bpp2, Format::Bpp3, Format::Bpp4].iter() {
+            let data = test_data(format.tile_len() * 5);
+            let tiles = decode(&data, *format);
+            assert_eq!(5, tiles.len());
+            for t in &tiles {
+                assert!(t.iter().all(|p| (*p as usize) < 1 << format.bpp()));
+            }
+            assert_eq!(data, encode(&tiles, *format));
+        }
+    }
+
+    #[test]
+    fn sheet_test() {
+        let tiles = decode(&test_data(32 * 5), Format::Bpp4);
+        let sheet = Sheet::from_tiles(&tiles, 2);
+        assert_eq!((16, 24), (sheet.width, sheet.height));
+        assert_eq!(tiles[..], sheet.to_tiles()[..5]);
+
+        let palette: Vec<Color> = (0..16)
+            .map(|i| Color {
+                r: i * 16,
+                g: 0,
+                b: 0xff - i * 16,
+                a: 0xff,
+            })
+            .collect();
+        let image = sheet.to_image(&palette);
+        assert_eq!(sheet, Sheet::from_image(&image, &palette).unwrap());
+        assert!(Sheet::from_image(&image, &palette[..2]).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::element::{self, Elements};
+use battle::status::{self, Status};
+use rom_map;
+use text;
+use utils::{check_len, test_bit};
+
+const ITEM_SIZE: usize = 0x1e;
+// Names start with an icon byte.
+const NAME_SIZE: usize = 13;
+
+// Item index used for empty slots in treasure, shops, steals, and drops.
+pub const EMPTY: u8 = 0xff;
+
+// Bits of the equippable characters field.  The top two are the Merit Award
+// and Imp restrictions.
+pub const CHARACTERS: [&str; 16] = [
+    "Terra",
+    "Locke",
+    "Cyan",
+    "Shadow",
+    "Edgar",
+    "Sabin",
+    "Celes",
+    "Strago",
+    "Relm",
+    "Setzer",
+    "Mog",
+    "Gau",
+    "Gogo",
+    "Umaro",
+    "Merit Award",
+    "Imp",
+];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Tool,
+    Weapon,
+    Armor,
+    Shield,
+    Helmet,
+    Relic,
+    Item,
+    Unused,
+}
+
+impl Kind {
+    fn from_bits(b: u8) -> Kind {
+        match b & 0x7 {
+            0 => Kind::Tool,
+            1 => Kind::Weapon,
+            2 => Kind::Armor,
+            3 => Kind::Shield,
+            4 => Kind::Helmet,
+            5 => Kind::Relic,
+            6 => Kind::Item,
+            _ => Kind::Unused,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Kind::Tool => 0,
+            Kind::Weapon => 1,
+            Kind::Armor => 2,
+            Kind::Shield => 3,
+            Kind::Helmet => 4,
+            Kind::Relic => 5,
+            Kind::Item => 6,
+            Kind::Unused => 7,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Item {
+    pub name: String,
+    pub icon: u8,
+    pub kind: Kind,
+    pub unknown_kind_bit_3: bool,
+    pub throwable: bool,
+    pub battle_usable: bool,
+    pub menu_usable: bool,
+    pub unknown_kind_bit_7: bool,
+
+    pub equippable: Vec<String>,
+    pub spell_learn_rate: u8,
+    pub spell_learned: u8,
+    pub field_effects: u8,
+    pub status_protection: Status,
+    pub equipment_status: Status,
+    pub relic_effects: [u8; 5],
+    pub targeting: u8,
+    pub element: Elements,
+
+    // Stat bonuses range from -7 to 7.
+    pub vigor: i8,
+    pub speed: i8,
+    pub stamina: i8,
+    pub magic: i8,
+    // Sign bits set on zero bonuses, in the order vigor, speed, stamina, and
+    // magic.
+    pub unknown_stat_signs: u8,
+
+    pub spell_cast: u8,
+    pub cast_randomly: bool,
+    pub breaks_after_cast: bool,
+    pub weapon_flags: u8,
+    // Battle power for weapons, defense for armor.
+    pub power: u8,
+    // Hit rate for weapons, magic defense for armor.
+    pub hit_rate: u8,
+    pub absorb: Elements,
+    pub null: Elements,
+    pub weak: Elements,
+    pub unknown_19: u8,
+    // Indexes into the evade bonus table.
+    pub evade: u8,
+    pub magic_evade: u8,
+    pub special_effect: u8,
+    pub price: u16,
+}
+
+fn decode_stat(n: u8) -> i8 {
+    if n & 0x8 == 0x8 {
+        -((n & 0x7) as i8)
+    } else {
+        (n & 0x7) as i8
+    }
+}
+
+// `negative` keeps the sign bit of a zero bonus.
+fn encode_stat(name: &str, s: i8, negative: bool) -> Result<u8, Box<Error>> {
+    if !(-7..=7).contains(&s) {
+        return Err(SimpleError::new(format!("{} bonus {} is not in -7 to 7", name, s)).into());
+    }
+    if s < 0 || negative {
+        Ok(0x8 | s.unsigned_abs())
+    } else {
+        Ok(s as u8)
+    }
+}
+
+fn decode_characters(bits: u16) -> Vec<String> {
+    CHARACTERS
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| (bits >> i) & 0x1 == 0x1)
+        .map(|(_, c)| c.to_string())
+        .collect()
+}
+
+fn encode_characters(names: &[String]) -> Result<u16, Box<Error>> {
+    let mut bits = 0;
+    for n in names {
+        match CHARACTERS.iter().position(|c| c == n) {
+            Some(i) => bits |= 1 << i,
+            None => return Err(SimpleError::new(format!("unknown character {}", n)).into()),
+        }
+    }
+    Ok(bits)
+}
+
+fn table(rom_data: &[u8], snes_addr: usize, size: usize) -> Result<&[u8], Box<Error>> {
+    let addr = rom_map::snes_to_file(snes_addr);
+    let end = addr + size * rom_map::NUM_ITEMS;
+    check_len(rom_data, addr, end - addr)?;
+    Ok(&rom_data[addr..end])
+}
+
+pub fn parse_item(data: &[u8], name: &[u8]) -> Result<Item, Box<Error>> {
+    if data.len() < ITEM_SIZE || name.len() < NAME_SIZE {
+        return Err(SimpleError::new("item data too short").into());
+    }
+
+    Ok(Item {
+        name: text::decode_name(&name[1..NAME_SIZE]),
+        icon: name[0],
+        kind: Kind::from_bits(data[0x00]),
+        unknown_kind_bit_3: test_bit(data[0x00], 3),
+        throwable: test_bit(data[0x00], 4),
+        battle_usable: test_bit(data[0x00], 5),
+        menu_usable: test_bit(data[0x00], 6),
+        unknown_kind_bit_7: test_bit(data[0x00], 7),
+
+        equippable: decode_characters(data[0x01] as u16 | (data[0x02] as u16) << 8),
+        spell_learn_rate: data[0x03],
+        spell_learned: data[0x04],
+        field_effects: data[0x05],
+        status_protection: status::decode(&data[0x06..0x08]),
+        equipment_status: status::decode(&[0, 0, data[0x08]]),
+        relic_effects: [data[0x09], data[0x0a], data[0x0b], data[0x0c], data[0x0d]],
+        targeting: data[0x0e],
+        element: element::decode(data[0x0f]),
+
+        vigor: decode_stat(data[0x10]),
+        speed: decode_stat(data[0x10] >> 4),
+        stamina: decode_stat(data[0x11]),
+        magic: decode_stat(data[0x11] >> 4),
+        unknown_stat_signs: [data[0x10], data[0x10] >> 4, data[0x11], data[0x11] >> 4]
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| *n & 0xf == 0x8)
+            .fold(0, |bits, (i, _)| bits | 1 << i),
+
+        spell_cast: data[0x12] & 0x3f,
+        cast_randomly: test_bit(data[0x12], 6),
+        breaks_after_cast: test_bit(data[0x12], 7),
+        weapon_flags: data[0x13],
+        power: data[0x14],
+        hit_rate: data[0x15],
+        absorb: element::decode(data[0x16]),
+        null: element::decode(data[0x17]),
+        weak: element::decode(data[0x18]),
+        unknown_19: data[0x19],
+        evade: data[0x1a] & 0xf,
+        magic_evade: data[0x1a] >> 4,
+        special_effect: data[0x1b],
+        price: data[0x1c] as u16 | (data[0x1d] as u16) << 8,
+    })
+}
+
+// Returns the item's data record and name.
+pub fn encode_item(item: &Item) -> Result<(Vec<u8>, Vec<u8>), Box<Error>> {
+    let flags = [
+        item.unknown_kind_bit_3,
+        item.throwable,
+        item.battle_usable,
+        item.menu_usable,
+        item.unknown_kind_bit_7,
+    ]
+    .iter()
+    .enumerate()
+    .fold(
+        item.kind.bits(),
+        |b, (i, set)| {
+            if *set {
+                b | 1 << (i + 3)
+            } else {
+                b
+            }
+        },
+    );
+    let equippable = encode_characters(&item.equippable)?;
+    let signs = item.unknown_stat_signs;
+    let vigor = encode_stat("vigor", item.vigor, test_bit(signs, 0))?;
+    let speed = encode_stat("speed", item.speed, test_bit(signs, 1))?;
+    let stamina = encode_stat("stamina", item.stamina, test_bit(signs, 2))?;
+    let magic = encode_stat("magic", item.magic, test_bit(signs, 3))?;
+    let status_protection = status::encode(&item.status_protection);
+
+    let mut data = vec![
+        flags,
+        equippable as u8,
+        (equippable >> 8) as u8,
+        item.spell_learn_rate,
+        item.spell_learned,
+        item.field_effects,
+        status_protection[0],
+        status_protection[1],
+        status::encode(&item.equipment_status)[2],
+    ];
+    data.extend_from_slice(&item.relic_effects);
+    data.extend_from_slice(&[
+        item.targeting,
+        element::encode(&item.element),
+        speed << 4 | vigor,
+        magic << 4 | stamina,
+        item.spell_cast & 0x3f
+            | (item.cast_randomly as u8) << 6
+            | (item.breaks_after_cast as u8) << 7,
+        item.weapon_flags,
+        item.power,
+        item.hit_rate,
+        element::encode(&item.absorb),
+        element::encode(&item.null),
+        element::encode(&item.weak),
+        item.unknown_19,
+        item.magic_evade << 4 | item.evade & 0xf,
+        item.special_effect,
+        item.price as u8,
+        (item.price >> 8) as u8,
+    ]);
+
+    let mut name = vec![item.icon];
+    name.extend(text::encode_name(&item.name, NAME_SIZE - 1)?);
+    Ok((data, name))
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Item>, Box<Error>> {
+    let data = table(rom_data, rom_map::ITEM_DATA, ITEM_SIZE)?;
+    let names = table(rom_data, rom_map::ITEM_NAMES, NAME_SIZE)?;
+
+    let mut items = Vec::new();
+    for i in 0..rom_map::NUM_ITEMS {
+        items.push(parse_item(&data[i * ITEM_SIZE..], &names[i * NAME_SIZE..])?);
+    }
+    Ok(items)
+}
+
+pub fn write(rom_data: &mut [u8], items: &[Item]) -> Result<(), Box<Error>> {
+    if items.len() != rom_map::NUM_ITEMS {
+        return Err(SimpleError::new(format!(
+            "expected {} items.  Got {}.",
+            rom_map::NUM_ITEMS,
+            items.len()
+        ))
+        .into());
+    }
+    table(rom_data, rom_map::ITEM_DATA, ITEM_SIZE)?;
+    table(rom_data, rom_map::ITEM_NAMES, NAME_SIZE)?;
+
+    let addr = rom_map::snes_to_file(rom_map::ITEM_DATA);
+    let names_addr = rom_map::snes_to_file(rom_map::ITEM_NAMES);
+    for (i, item) in items.iter().enumerate() {
+        let (data, name) = encode_item(item)?;
+        let start = addr + i * ITEM_SIZE;
+        rom_data[start..start + ITEM_SIZE].copy_from_slice(&data);
+        let start = names_addr + i * NAME_SIZE;
+        rom_data[start..start + NAME_SIZE].copy_from_slice(&name);
+    }
+    Ok(())
+}
+
+// Resolves an item index as found in treasure, shops, steals, drops, and
+// event scripts.
+pub fn name(items: &[Item], index: u8) -> String {
+    match items.get(index as usize) {
+        _ if index == EMPTY => String::from("(none)"),
+        Some(item) => item.name.clone(),
+        None => format!("item {:02x}", index),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_test() {
+        // Dirk
+        let data = [
+            0x31, 0xff, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x1a, 0xb4, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x96, 0x00,
+        ];
+        let name_data = [
+            0xd8, 0x83, 0xa2, 0xab, 0xa4, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        ];
+        let item = parse_item(&data, &name_data).unwrap();
+        assert_eq!("Dirk", item.name);
+        assert_eq!(Kind::Weapon, item.kind);
+        assert!(item.throwable && item.battle_usable);
+        assert_eq!(14, item.equippable.len());
+        assert_eq!(26, item.power);
+        assert_eq!(150, item.price);
+
+        let (d, n) = encode_item(&item).unwrap();
+        assert_eq!(data.to_vec(), d);
+        assert_eq!(name_data.to_vec(), n);
+
+        assert_eq!(-3, decode_stat(0xb));
+        assert_eq!(0xb, encode_stat("vigor", -3, false).unwrap());
+
+        // Zero bonuses keep their sign bit.
+        let mut signed = data;
+        signed[0x10] = 0x8b;
+        let mut item = parse_item(&signed, &name_data).unwrap();
+        assert_eq!(
+            (-3, 0, 0x2),
+            (item.vigor, item.speed, item.unknown_stat_signs)
+        );
+        assert_eq!(signed.to_vec(), encode_item(&item).unwrap().0);
+        item.magic = -128;
+        assert!(encode_item(&item).is_err());
+        item.magic = 8;
+        assert!(encode_item(&item).is_err());
+        assert_eq!("Dirk", name(&[item], 0));
+        assert_eq!("(none)", name(&[], EMPTY));
+    }
+}
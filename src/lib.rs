@@ -1,8 +1,17 @@
+pub mod battle;
+pub mod characters;
+pub mod compression;
 pub mod events;
 pub mod field;
+pub mod graphics;
+pub mod items;
 pub mod ptr_table;
 pub mod rom_map;
+pub mod shops;
+pub mod sound;
+pub mod text;
 pub mod utils;
+pub mod world;
 
 #[macro_use]
 extern crate nom;
@@ -10,5 +19,8 @@ extern crate nom;
 #[macro_use]
 extern crate serde_derive;
 
+extern crate png;
 extern crate serde;
+extern crate serde_json;
 extern crate simple_error;
+extern crate xml;
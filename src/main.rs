@@ -1,11 +1,48 @@
 extern crate ff6;
 extern crate ron;
 
+use ff6::battle;
+use ff6::battle::abilities;
+use ff6::battle::ai;
+use ff6::battle::background;
+use ff6::battle::encounter;
+use ff6::battle::esper;
+use ff6::battle::formation;
+use ff6::battle::magic;
+use ff6::battle::monster;
+use ff6::battle::sprite as monster_sprite;
+use ff6::characters;
+use ff6::compression;
 use ff6::field;
+use ff6::field::annotate;
+use ff6::field::collision;
+use ff6::field::exit;
+use ff6::field::graph::Graph;
+use ff6::field::npc;
+use ff6::field::render;
+use ff6::field::sprite;
+use ff6::field::tmx;
+use ff6::field::treasure;
+use ff6::field::trigger;
+use ff6::graphics::font;
+use ff6::graphics::palette;
+use ff6::graphics::tile::Sheet;
+use ff6::graphics::Image;
+use ff6::items;
+use ff6::ptr_table;
+use ff6::rom_map;
+use ff6::shops;
+use ff6::sound::brr;
+use ff6::sound::song;
+use ff6::sound::spc;
+use ff6::world;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fs::File;
-use std::fs::{create_dir_all, write};
+use std::fs::{create_dir_all, read_to_string, write};
 use std::io::Error;
 use std::io::Read;
+use std::path::Path;
 
 struct Rom {
     data: Vec<u8>,
@@ -28,11 +65,903 @@ impl Rom {
             write(format!("out/field/{:03x}.ron", l), t).unwrap();
         }
     }
+
+    fn render_locations(&self, annotated: bool, outline: bool) {
+        let locations = field::parse(&self.data).unwrap();
+        create_dir_all("out/render/").unwrap();
+        for (l, location) in locations.iter().enumerate() {
+            let layers = match render::render(&self.data, location) {
+                Ok(layers) => layers,
+                Err(e) => {
+                    println!("location {:03x}: {}", l, e);
+                    continue;
+                }
+            };
+            let path = |name: &str| format!("out/render/{:03x}_{}.png", l, name);
+            layers.bg1.write_png(Path::new(&path("bg1"))).unwrap();
+            if let Some(bg2) = layers.bg2 {
+                bg2.write_png(Path::new(&path("bg2"))).unwrap();
+            }
+            if let Some(bg3) = layers.bg3 {
+                bg3.write_png(Path::new(&path("bg3"))).unwrap();
+            }
+            layers
+                .composite
+                .write_png(Path::new(&path("composite")))
+                .unwrap();
+
+            if annotated {
+                annotate::annotate(&self.data, &layers.composite, location, outline)
+                    .write_png(Path::new(&path("annotated")))
+                    .unwrap();
+                write(
+                    format!("out/render/{:03x}_legend.txt", l),
+                    annotate::legend(location),
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    // Writes a text collision grid for every location and reports NPCs and
+    // triggers placed on tiles that can't be walked on.
+    fn check_collision(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        create_dir_all("out/collision/").unwrap();
+        for (l, location) in locations.iter().enumerate() {
+            let map = match collision::collision_map(&self.data, location) {
+                Ok(map) => map,
+                Err(e) => {
+                    println!("location {:03x}: {}", l, e);
+                    continue;
+                }
+            };
+            let mut text = String::new();
+            for y in 0..map.height {
+                for x in 0..map.width {
+                    let p = map.get(x, y).unwrap();
+                    text.push(if p.is_impassable() {
+                        '#'
+                    } else if p.door {
+                        'D'
+                    } else if p.stairs_up_left || p.stairs_up_right {
+                        'S'
+                    } else if p.counter {
+                        'C'
+                    } else if p.is_bridge() {
+                        'B'
+                    } else if p.is_walkable() {
+                        '.'
+                    } else {
+                        ' '
+                    });
+                }
+                text.push('\n');
+            }
+            write(format!("out/collision/{:03x}.txt", l), text).unwrap();
+
+            for m in collision::blocked_markers(location, &map) {
+                println!(
+                    "location {:03x}: {} at ({}, {}) is not walkable",
+                    l,
+                    m.label(),
+                    m.x,
+                    m.y
+                );
+            }
+        }
+    }
+
+    // Reads every location RON file in dir and writes their exits and
+    // treasure back to the ROM.
+    fn import_locations(&mut self, dir: &str) {
+        let mut entrance_events = Vec::new();
+        let mut triggers = Vec::new();
+        let mut npcs = Vec::new();
+        let mut short_exits = Vec::new();
+        let mut long_exits = Vec::new();
+        let mut treasures = Vec::new();
+        for l in 0..rom_map::NUM_LOCATIONS {
+            let text = read_to_string(format!("{}/{:03x}.ron", dir, l)).unwrap();
+            let location: field::Location = ron::de::from_str(&text).unwrap();
+            entrance_events.push(location.entrance_event_addr);
+            triggers.push(location.triggers);
+            npcs.push(location.npcs);
+            short_exits.push(location.short_exits);
+            long_exits.push(location.long_exits);
+            treasures.push(location.treasures);
+        }
+        field::write_entrance_events(&mut self.data, &entrance_events).unwrap();
+        trigger::write_triggers(&mut self.data, &triggers).unwrap();
+        npc::write_npcs(&mut self.data, &npcs).unwrap();
+        exit::write_short_exits(&mut self.data, &short_exits).unwrap();
+        exit::write_long_exits(&mut self.data, &long_exits).unwrap();
+        treasure::write_treasures(&mut self.data, &treasures).unwrap();
+        self.save();
+    }
+
+    fn location_graph(&self) -> Graph {
+        let locations = field::parse(&self.data).unwrap();
+        Graph::from_rom(&self.data, &locations)
+    }
+
+    fn export_graph(&self, start: &str) {
+        let start = u16::from_str_radix(start, 16).unwrap();
+        let graph = self.location_graph();
+        create_dir_all("out/graph/").unwrap();
+        write("out/graph/locations.dot", graph.to_dot()).unwrap();
+        write("out/graph/locations.json", graph.to_json().unwrap()).unwrap();
+        for l in graph.unreachable(start) {
+            println!("location {:03x} unreachable from {:03x}", l, start);
+        }
+    }
+
+    fn print_route(&self, from: &str, to: &str) {
+        let from = u16::from_str_radix(from, 16).unwrap();
+        let to = u16::from_str_radix(to, 16).unwrap();
+        let graph = self.location_graph();
+        match graph.shortest_route(from, to) {
+            Some(route) => {
+                for e in route {
+                    println!(
+                        "{:03x} -> {:03x} ({:?} {}) at ({}, {})",
+                        e.from, e.to, e.kind, e.index, e.dest.x, e.dest.y
+                    );
+                }
+            }
+            None => println!("no route from {:03x} to {:03x}", from, to),
+        }
+    }
+
+    // Lists the formations which can appear in every location and world
+    // map zone.
+    fn export_encounters(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        let packs = encounter::formation_packs(&self.data).unwrap();
+        let formations = |pack: u8| {
+            packs[pack as usize]
+                .formations
+                .iter()
+                .map(|f| format!("{:03x}", f))
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        create_dir_all("out/encounters/").unwrap();
+
+        let mut text = String::new();
+        for e in encounter::location_encounters(&self.data, &locations).unwrap() {
+            text.push_str(&format!(
+                "{:03x} {:<5} {:<6?} pack {:02x}: {}\n",
+                e.location,
+                e.enabled,
+                e.rate,
+                e.pack,
+                formations(e.pack)
+            ));
+        }
+        write("out/encounters/locations.txt", text).unwrap();
+
+        let mut text = String::new();
+        for world in [encounter::World::Balance, encounter::World::Ruin].iter() {
+            for z in encounter::zone_encounters(&self.data, *world).unwrap() {
+                for (t, terrain) in encounter::TERRAINS.iter().enumerate() {
+                    text.push_str(&format!(
+                        "{:?} zone {:02x} {:<6} {:<6?} pack {:02x}: {}\n",
+                        z.world,
+                        z.zone,
+                        terrain,
+                        z.rates[t],
+                        z.packs[t],
+                        formations(z.packs[t])
+                    ));
+                }
+            }
+        }
+        write("out/encounters/zones.txt", text).unwrap();
+    }
+
+    fn export_formations(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        let monsters = battle::parse_monsters(&self.data).unwrap();
+        let formations = formation::parse(&self.data).unwrap();
+        let packs = encounter::formation_packs(&self.data).unwrap();
+        let mut zones = encounter::zone_encounters(&self.data, encounter::World::Balance).unwrap();
+        zones.extend(encounter::zone_encounters(&self.data, encounter::World::Ruin).unwrap());
+        let refs = formation::references(
+            &packs,
+            &locations,
+            &encounter::location_encounters(&self.data, &locations).unwrap(),
+            &zones,
+        );
+
+        create_dir_all("out/battle/").unwrap();
+        let config = ron::ser::PrettyConfig::default();
+        let t = ron::ser::to_string_pretty(&formations, config.clone()).unwrap();
+        write("out/battle/formations.ron", t).unwrap();
+        let t = ron::ser::to_string_pretty(&packs, config).unwrap();
+        write("out/battle/packs.ron", t).unwrap();
+
+        let mut text = String::new();
+        for (i, f) in formations.iter().enumerate() {
+            let names: Vec<String> = f
+                .slots
+                .iter()
+                .filter(|s| s.monster != formation::NO_MONSTER)
+                .map(|s| {
+                    let name = monsters
+                        .get(s.monster as usize)
+                        .map(|m| m.name.as_str())
+                        .unwrap_or("?");
+                    if s.visible {
+                        name.to_string()
+                    } else {
+                        format!("({})", name)
+                    }
+                })
+                .collect();
+            text.push_str(&format!(
+                "{:03x} music {} event {:02x}: {}\n",
+                i,
+                f.music,
+                f.special_event,
+                names.join(", ")
+            ));
+            for r in refs.get(&(i as u16)).unwrap_or(&Vec::new()) {
+                text.push_str(&format!("    {:?}\n", r));
+            }
+        }
+        write("out/battle/formations.txt", text).unwrap();
+    }
+
+    fn import_formations(&mut self, dir: &str) {
+        let text = read_to_string(format!("{}/formations.ron", dir)).unwrap();
+        let formations: Vec<formation::Formation> = ron::de::from_str(&text).unwrap();
+        formation::write(&mut self.data, &formations).unwrap();
+        let text = read_to_string(format!("{}/packs.ron", dir)).unwrap();
+        let packs: Vec<encounter::FormationPack> = ron::de::from_str(&text).unwrap();
+        encounter::write_formation_packs(&mut self.data, &packs).unwrap();
+        self.save();
+    }
+
+    fn render_world_maps(&self) {
+        create_dir_all("out/world/").unwrap();
+        for map in world::Map::all().iter() {
+            let w = match world::parse(&self.data, *map) {
+                Ok(w) => w,
+                Err(e) => {
+                    println!("{}: {}", map.name(), e);
+                    continue;
+                }
+            };
+            let path = |name: &str| format!("out/world/{}{}", map.name(), name);
+            let image = w.render();
+            image.write_png(Path::new(&path(".png"))).unwrap();
+            if let Some(world) = map.world() {
+                let zones = encounter::zone_encounters(&self.data, world).unwrap();
+                world::overlay_zones(&w, &image, &zones)
+                    .write_png(Path::new(&path("_zones.png")))
+                    .unwrap();
+                write(path("_terrain.txt"), w.terrain_grid()).unwrap();
+            }
+        }
+    }
+
+    fn render_battle_backgrounds(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        let users = background::locations_by_background(&locations);
+        create_dir_all("out/battle/backgrounds/").unwrap();
+        let mut text = String::new();
+        for (i, bg) in background::parse(&self.data).unwrap().iter().enumerate() {
+            match background::render(&self.data, bg) {
+                Ok(image) => image
+                    .write_png(Path::new(&format!("out/battle/backgrounds/{:02x}.png", i)))
+                    .unwrap(),
+                Err(e) => println!("battle background {:02x}: {}", i, e),
+            }
+            let locations: Vec<String> = users
+                .get(&(i as u8))
+                .unwrap_or(&Vec::new())
+                .iter()
+                .map(|l| format!("{:03x}", l))
+                .collect();
+            text.push_str(&format!("{:02x}: {}\n", i, locations.join(" ")));
+        }
+        write("out/battle/backgrounds/locations.txt", text).unwrap();
+    }
+
+    fn export_monsters(&self) {
+        let monsters = battle::parse_monsters(&self.data).unwrap();
+        create_dir_all("out/battle/").unwrap();
+        let t = ron::ser::to_string_pretty(&monsters, ron::ser::PrettyConfig::default()).unwrap();
+        write("out/battle/monsters.ron", t).unwrap();
+
+        let mut csv = String::from(monster::CSV_HEADER);
+        csv.push('\n');
+        for (i, m) in monsters.iter().enumerate() {
+            csv.push_str(&monster::csv_row(i, m));
+            csv.push('\n');
+        }
+        write("out/battle/monsters.csv", csv).unwrap();
+    }
+
+    fn export_magic(&self) {
+        let spells = magic::parse(&self.data).unwrap();
+        let espers = esper::parse(&self.data).unwrap();
+        let natural = [
+            magic::natural_magic(&self.data, 0).unwrap(),
+            magic::natural_magic(&self.data, 1).unwrap(),
+        ];
+        create_dir_all("out/magic/").unwrap();
+        let config = ron::ser::PrettyConfig::default();
+        let t = ron::ser::to_string_pretty(&spells, config.clone()).unwrap();
+        write("out/magic/spells.ron", t).unwrap();
+        let t = ron::ser::to_string_pretty(&espers, config.clone()).unwrap();
+        write("out/magic/espers.ron", t).unwrap();
+        let t = ron::ser::to_string_pretty(&natural, config).unwrap();
+        write("out/magic/natural.ron", t).unwrap();
+
+        let mut text = String::new();
+        for (i, e) in espers.iter().enumerate() {
+            let learned: Vec<String> = e
+                .spells
+                .iter()
+                .filter(|l| l.spell != esper::NONE)
+                .map(|l| format!("{} x{}", spells[l.spell as usize].name, l.rate))
+                .collect();
+            text.push_str(&format!(
+                "{:<8} {:<20} {}\n",
+                spells[esper::FIRST_ESPER_SPELL + i].name,
+                e.bonus()
+                    .map(|b| format!("{:?}", b))
+                    .unwrap_or_else(|| String::from("-")),
+                learned.join(", ")
+            ));
+        }
+        write("out/magic/espers.txt", text).unwrap();
+    }
+
+    fn import_magic(&mut self, dir: &str) {
+        let text = read_to_string(format!("{}/spells.ron", dir)).unwrap();
+        let spells: Vec<magic::Spell> = ron::de::from_str(&text).unwrap();
+        magic::write(&mut self.data, &spells).unwrap();
+        let text = read_to_string(format!("{}/espers.ron", dir)).unwrap();
+        let espers: Vec<esper::Esper> = ron::de::from_str(&text).unwrap();
+        esper::write(&mut self.data, &espers).unwrap();
+        let text = read_to_string(format!("{}/natural.ron", dir)).unwrap();
+        let natural: Vec<Vec<magic::NaturalMagic>> = ron::de::from_str(&text).unwrap();
+        for (c, n) in natural.iter().enumerate() {
+            magic::write_natural_magic(&mut self.data, c, n).unwrap();
+        }
+        self.save();
+    }
+
+    fn export_characters(&self) {
+        let characters = characters::parse(&self.data).unwrap();
+        let growth = characters::parse_growth(&self.data).unwrap();
+        create_dir_all("out/characters/").unwrap();
+        let config = ron::ser::PrettyConfig::default();
+        let t = ron::ser::to_string_pretty(&characters, config.clone()).unwrap();
+        write("out/characters/characters.ron", t).unwrap();
+        let t = ron::ser::to_string_pretty(&growth, config).unwrap();
+        write("out/characters/growth.ron", t).unwrap();
+    }
+
+    fn import_characters(&mut self, dir: &str) {
+        let text = read_to_string(format!("{}/characters.ron", dir)).unwrap();
+        let characters: Vec<characters::Character> = ron::de::from_str(&text).unwrap();
+        characters::write(&mut self.data, &characters).unwrap();
+        let text = read_to_string(format!("{}/growth.ron", dir)).unwrap();
+        let growth: characters::Growth = ron::de::from_str(&text).unwrap();
+        characters::write_growth(&mut self.data, &growth).unwrap();
+        self.save();
+    }
+
+    // Prints a character's projected stats at `level` with each esper level
+    // up bonus.
+    fn print_projection(&self, c: &str, level: &str) {
+        let c = usize::from_str_radix(c, 16).unwrap();
+        let level = level.parse::<u8>().unwrap();
+        let character = &characters::parse(&self.data).unwrap()[c];
+        let growth = characters::parse_growth(&self.data).unwrap();
+        let mut bonuses = vec![None];
+        bonuses.extend((0..=0x10).filter_map(|b| esper::decode_bonus(b).map(Some)));
+        println!(
+            "{} level {} ({} xp)",
+            character.name,
+            level,
+            growth.experience_for(level)
+        );
+        for b in bonuses {
+            let s = characters::project(character, &growth, level, b);
+            println!(
+                "{:<16} hp {:>4} mp {:>3} vigor {:>3} speed {:>3} stamina {:>3} magic {:>3}",
+                b.map(|b| format!("{:?}", b))
+                    .unwrap_or_else(|| String::from("none")),
+                s.hp,
+                s.mp,
+                s.vigor,
+                s.speed,
+                s.stamina,
+                s.magic_power
+            );
+        }
+    }
+
+    fn export_abilities(&self) {
+        let spells = magic::parse(&self.data).unwrap();
+        let monsters = battle::parse_monsters(&self.data).unwrap();
+        let a = abilities::parse(&self.data, &spells).unwrap();
+        create_dir_all("out/battle/").unwrap();
+        let t = ron::ser::to_string_pretty(&a, ron::ser::PrettyConfig::default()).unwrap();
+        write("out/battle/abilities.ron", t).unwrap();
+        write(
+            "out/battle/abilities.txt",
+            abilities::to_text(&a, &spells, &monsters),
+        )
+        .unwrap();
+    }
+
+    fn export_items(&self) {
+        let items = items::parse(&self.data).unwrap();
+        create_dir_all("out/items/").unwrap();
+        let t = ron::ser::to_string_pretty(&items, ron::ser::PrettyConfig::default()).unwrap();
+        write("out/items/items.ron", t).unwrap();
+
+        let mut text = String::new();
+        for (i, item) in items.iter().enumerate() {
+            text.push_str(&format!(
+                "{:02x} {:<12} {:<6?} {:>5} gp\n",
+                i, item.name, item.kind, item.price
+            ));
+        }
+        write("out/items/items.txt", text).unwrap();
+    }
+
+    fn import_items(&mut self, file: &str) {
+        let text = read_to_string(file).unwrap();
+        let items: Vec<items::Item> = ron::de::from_str(&text).unwrap();
+        items::write(&mut self.data, &items).unwrap();
+        self.save();
+    }
+
+    fn export_shops(&self) {
+        let items = items::parse(&self.data).unwrap();
+        let shops = shops::parse(&self.data).unwrap();
+        create_dir_all("out/shops/").unwrap();
+        let t = ron::ser::to_string_pretty(&shops, ron::ser::PrettyConfig::default()).unwrap();
+        write("out/shops/shops.ron", t).unwrap();
+
+        let mut text = String::new();
+        for (i, s) in shops.iter().enumerate() {
+            text.push_str(&format!(
+                "{:02x} {:<6?} price {}: {}\n",
+                i,
+                s.kind,
+                s.price_modifier,
+                s.item_names(&items).join(", ")
+            ));
+        }
+        write("out/shops/shops.txt", text).unwrap();
+    }
+
+    fn import_shops(&mut self, file: &str) {
+        let items = items::parse(&self.data).unwrap();
+        let text = read_to_string(file).unwrap();
+        let shops: Vec<shops::Shop> = ron::de::from_str(&text).unwrap();
+        shops::write(&mut self.data, &shops, &items).unwrap();
+        self.save();
+    }
+
+    fn export_monster_sprites(&self) {
+        let monsters = battle::parse_monsters(&self.data).unwrap();
+        create_dir_all("out/battle/sprites/").unwrap();
+        let mut text = String::new();
+        for (i, m) in monsters.iter().enumerate() {
+            match monster_sprite::render(&self.data, i) {
+                Ok(image) => image
+                    .write_png(Path::new(&format!("out/battle/sprites/{:03x}.png", i)))
+                    .unwrap(),
+                Err(e) => println!("monster {:03x}: {}", i, e),
+            }
+            text.push_str(&format!("{:03x} {}\n", i, m.name));
+        }
+        write("out/battle/sprites/index.txt", text).unwrap();
+    }
+
+    fn export_monster_ai(&self) {
+        let monsters = battle::parse_monsters(&self.data).unwrap();
+        let scripts = ai::parse(&self.data).unwrap();
+        let names: Vec<String> = magic::parse(&self.data)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        create_dir_all("out/battle/ai/").unwrap();
+        for (i, (m, s)) in monsters.iter().zip(scripts.iter()).enumerate() {
+            let text = format!("{:03x} {}\n{}", i, m.name, ai::to_text(s, &names));
+            write(format!("out/battle/ai/{:03x}.txt", i), text).unwrap();
+        }
+    }
+
+    fn save(&self) {
+        create_dir_all("out/").unwrap();
+        write("out/ff3.sfc", &self.data).unwrap();
+    }
+
+    fn export_palettes(&self) {
+        for kind in palette::Kind::all().iter() {
+            let dir = format!("out/palettes/{}/", kind.name());
+            create_dir_all(&dir).unwrap();
+            for i in 0..kind.count() {
+                let p = palette::read(&self.data, *kind, i).unwrap();
+                let name = format!("{} {:03x}", kind.name(), i);
+                let path = format!("{}{:03x}", dir, i);
+                p.to_swatch()
+                    .write_png(Path::new(&format!("{}.png", path)))
+                    .unwrap();
+                write(format!("{}.gpl", path), p.to_gpl(&name)).unwrap();
+                write(format!("{}.json", path), p.to_json().unwrap()).unwrap();
+            }
+        }
+    }
+
+    fn import_palette(&mut self, kind: &str, index: &str, file: &str) {
+        let kind = palette::Kind::from_name(kind).unwrap();
+        let index = usize::from_str_radix(index, 16).unwrap();
+        let text = read_to_string(file).unwrap();
+        let p = if file.ends_with(".json") {
+            palette::Palette::from_json(&text).unwrap()
+        } else {
+            palette::Palette::from_gpl(&text).unwrap()
+        };
+        palette::write(&mut self.data, kind, index, &p).unwrap();
+        self.save();
+    }
+
+    // NPCs select one of the sprite palettes.  Use the first one found for
+    // each sprite so exported sheets look like they do in game.
+    fn sprite_palettes(&self) -> HashMap<usize, usize> {
+        let mut palettes = HashMap::new();
+        for location in field::parse(&self.data).unwrap() {
+            for npc in location.npcs {
+                palettes
+                    .entry(npc.sprite as usize)
+                    .or_insert(npc.palette as usize);
+            }
+        }
+        palettes
+    }
+
+    fn export_sprites(&self) {
+        let palettes = self.sprite_palettes();
+        create_dir_all("out/sprites/").unwrap();
+        for s in 0..rom_map::NUM_SPRITES {
+            let sheet = match sprite::sheet(&self.data, s) {
+                Ok(sheet) => sheet,
+                Err(e) => {
+                    println!("sprite {:02x}: {}", s, e);
+                    continue;
+                }
+            };
+            let p = palettes.get(&s).cloned().unwrap_or(0);
+            let colors = palette::read(&self.data, palette::Kind::Sprite, p).unwrap();
+            sheet
+                .to_image(&colors.colors)
+                .write_png(Path::new(&format!("out/sprites/{:02x}.png", s)))
+                .unwrap();
+        }
+    }
+
+    fn import_sprite(&mut self, s: &str, file: &str) {
+        let s = usize::from_str_radix(s, 16).unwrap();
+        let p = self.sprite_palettes().get(&s).cloned().unwrap_or(0);
+        let colors = palette::read(&self.data, palette::Kind::Sprite, p).unwrap();
+        let image = Image::read_png(Path::new(file)).unwrap();
+        let sheet = Sheet::from_image(&image, &colors.colors).unwrap();
+        sprite::write_sheet(&mut self.data, s, &sheet).unwrap();
+        self.save();
+    }
+
+    fn export_music(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        let users = song::locations_by_song(&locations);
+        let engine = song::engine(&self.data).unwrap();
+        let samples = song::parse_samples(&self.data).unwrap();
+        let songs = song::parse_songs(&self.data);
+        create_dir_all("out/music/samples/").unwrap();
+        for (i, s) in samples.iter().enumerate() {
+            let wav = brr::to_wav(&brr::decode(&s.brr), brr::SAMPLE_RATE);
+            write(format!("out/music/samples/{:02x}.wav", i), wav).unwrap();
+        }
+        let mut text = String::new();
+        for (i, s) in songs.iter().enumerate() {
+            let s = match s {
+                Ok(s) => s,
+                Err(e) => {
+                    println!("song {:02x}: {}", i, e);
+                    text.push_str(&format!("{:02x}: {}\n", i, e));
+                    continue;
+                }
+            };
+            match spc::build(&engine, s, i, &samples) {
+                Ok(data) => write(format!("out/music/{:02x}.spc", i), data).unwrap(),
+                Err(e) => println!("song {:02x}: {}", i, e),
+            }
+            let channels: Vec<String> = s.channels().iter().map(|c| format!("{:04x}", c)).collect();
+            let samples: Vec<String> = s.samples().iter().map(|s| format!("{:02x}", s)).collect();
+            let locations: Vec<String> = users
+                .get(&(i as u8))
+                .unwrap_or(&Vec::new())
+                .iter()
+                .map(|l| format!("{:03x}", l))
+                .collect();
+            text.push_str(&format!(
+                "{:02x}: {:04x} bytes\n  channels: {}\n  samples: {}\n  locations: {}\n",
+                i,
+                s.data.len(),
+                channels.join(" "),
+                samples.join(" "),
+                locations.join(" ")
+            ));
+        }
+        write("out/music/songs.txt", text).unwrap();
+    }
+
+    fn export_fonts(&self) {
+        create_dir_all("out/fonts/").unwrap();
+        let small = font::parse_small(&self.data).unwrap();
+        font::small_sheet(&small)
+            .to_image(&font::palette())
+            .write_png(Path::new("out/fonts/small.png"))
+            .unwrap();
+        let dialog = font::parse_dialog(&self.data).unwrap();
+        font::dialog_sheet(&dialog)
+            .to_image(&font::palette())
+            .write_png(Path::new("out/fonts/dialog.png"))
+            .unwrap();
+        font::dialog_preview(&dialog)
+            .write_png(Path::new("out/fonts/dialog_widths.png"))
+            .unwrap();
+        let widths: Vec<u8> = dialog.iter().map(|g| g.width).collect();
+        let t = ron::ser::to_string_pretty(&widths, ron::ser::PrettyConfig::default()).unwrap();
+        write("out/fonts/dialog_widths.ron", t).unwrap();
+    }
+
+    fn import_fonts(&mut self, dir: &str) {
+        let image = Image::read_png(Path::new(&format!("{}/small.png", dir))).unwrap();
+        let small = font::small_from_image(&image).unwrap();
+        font::write_small(&mut self.data, &small).unwrap();
+        let text = read_to_string(format!("{}/dialog_widths.ron", dir)).unwrap();
+        let widths: Vec<u8> = ron::de::from_str(&text).unwrap();
+        let image = Image::read_png(Path::new(&format!("{}/dialog.png", dir))).unwrap();
+        let dialog = font::dialog_from_image(&image, &widths).unwrap();
+        font::write_dialog(&mut self.data, &dialog).unwrap();
+        self.save();
+    }
+
+    fn export_tmx(&self) {
+        let locations = field::parse(&self.data).unwrap();
+        create_dir_all("out/tmx/").unwrap();
+        for (l, location) in locations.iter().enumerate() {
+            let prefix = format!("{:03x}", l);
+            let tilemaps = render::tilemaps(&self.data, location);
+            let tilesets = render::tileset_images(&self.data, location);
+            let (tilemaps, tilesets) = match (tilemaps, tilesets) {
+                (Ok(m), Ok(s)) => (m, s),
+                (Err(e), _) | (_, Err(e)) => {
+                    println!("location {:03x}: {}", l, e);
+                    continue;
+                }
+            };
+            for (layer, image) in tilesets.iter().enumerate() {
+                if let Some(image) = image {
+                    let name = tmx::tileset_image_name(&prefix, layer);
+                    image
+                        .write_png(Path::new(&format!("out/tmx/{}", name)))
+                        .unwrap();
+                }
+            }
+            write(
+                format!("out/tmx/{}.tmx", prefix),
+                tmx::export(location, &tilemaps, &prefix),
+            )
+            .unwrap();
+        }
+    }
+
+    // Imports an edited map as a location RON dump and compressed tilemaps.
+    // Tilemaps aren't written back to the ROM so the import is refused if
+    // any of them were edited.
+    fn import_tmx(&self, l: &str, file: &str) {
+        let l = usize::from_str_radix(l, 16).unwrap();
+        let locations = field::parse(&self.data).unwrap();
+        let text = read_to_string(file).unwrap();
+        let import = tmx::import(&text, &locations[l]).unwrap();
+        let tilemaps = render::tilemaps(&self.data, &locations[l]).unwrap();
+        for (layer, (a, b)) in import.tilemaps.iter().zip(tilemaps.iter()).enumerate() {
+            if a.is_some() && a != b {
+                panic!(
+                    "bg{} tilemap of location {:03x} was edited.  Tilemap import isn't supported.",
+                    layer + 1,
+                    l
+                );
+            }
+        }
+
+        create_dir_all("out/field/").unwrap();
+        let t = ron::ser::to_string_pretty(&import.location, ron::ser::PrettyConfig::default())
+            .unwrap();
+        write(format!("out/field/{:03x}.ron", l), t).unwrap();
+    }
+
+    fn u24_table_blocks(
+        &self,
+        blocks: &mut BTreeMap<usize, String>,
+        name: &str,
+        ptrs: usize,
+        base: usize,
+        num: usize,
+    ) {
+        let addr = rom_map::snes_to_file(ptrs);
+        let table =
+            ptr_table::Table::new_u24(&self.data[addr..], num + 1, rom_map::snes_to_file(base));
+        for (i, e) in table.entries.iter().enumerate() {
+            blocks
+                .entry(e.addr)
+                .or_insert(format!("{} {:03x}", name, i));
+        }
+    }
+
+    fn u16_table_blocks(
+        &self,
+        blocks: &mut BTreeMap<usize, String>,
+        name: &str,
+        ptrs: usize,
+        base: usize,
+        num: usize,
+    ) {
+        let addr = rom_map::snes_to_file(ptrs);
+        for i in 0..num {
+            let p = addr + i * 2;
+            let offset = self.data[p] as usize | (self.data[p + 1] as usize) << 8;
+            blocks
+                .entry(rom_map::snes_to_file(base) + offset)
+                .or_insert(format!("{} {:03x}", name, i));
+        }
+    }
+
+    fn compressed_blocks(&self) -> BTreeMap<usize, String> {
+        let mut blocks = BTreeMap::new();
+        self.u16_table_blocks(
+            &mut blocks,
+            "tile properties",
+            rom_map::MAP_TILE_PROPERTIES_POINTERS,
+            rom_map::MAP_TILE_PROPERTIES,
+            rom_map::NUM_MAP_TILE_PROPERTIES,
+        );
+        self.u24_table_blocks(
+            &mut blocks,
+            "tilemap",
+            rom_map::MAP_TILEMAP_POINTERS,
+            rom_map::MAP_TILEMAPS,
+            rom_map::NUM_MAP_TILEMAPS,
+        );
+        self.u24_table_blocks(
+            &mut blocks,
+            "tileset",
+            rom_map::MAP_TILESET_POINTERS,
+            rom_map::MAP_TILESETS,
+            rom_map::NUM_MAP_TILESETS,
+        );
+        self.u24_table_blocks(
+            &mut blocks,
+            "bg3 graphics",
+            rom_map::MAP_BG3_GRAPHICS_POINTERS,
+            rom_map::MAP_BG3_GRAPHICS,
+            rom_map::NUM_MAP_BG3_GRAPHICS,
+        );
+        for map in world::Map::all().iter() {
+            for addr in [map.tilemap_addr(), map.graphics_addr()].iter() {
+                blocks
+                    .entry(rom_map::snes_to_file(*addr))
+                    .or_insert(format!("world {}", map.name()));
+            }
+        }
+        for bg in background::parse(&self.data).unwrap() {
+            for set in bg
+                .graphics
+                .iter()
+                .filter(|g| **g != background::NO_GRAPHICS)
+            {
+                let addr = background::graphics_set_addr(&self.data, *set).unwrap();
+                blocks
+                    .entry(addr)
+                    .or_insert(format!("battle bg graphics {:02x}", set));
+            }
+            let addr = background::tilemap_addr(&self.data, bg.tilemap).unwrap();
+            blocks
+                .entry(addr)
+                .or_insert(format!("battle bg tilemap {:02x}", bg.tilemap));
+        }
+        blocks
+    }
+
+    fn compression_bench(&self) {
+        let mut totals = [0usize; 3];
+        println!(
+            "{:<20} {:>8} {:>8} {:>8}",
+            "block", "vanilla", "fast", "optimal"
+        );
+        for (addr, name) in self.compressed_blocks() {
+            let vanilla = compression::compressed_len(&self.data[addr..]).unwrap();
+            let data = compression::decompress(&self.data[addr..]).unwrap();
+            let fast = compression::compress(&data, compression::Mode::Fast).unwrap();
+            let optimal = compression::compress(&data, compression::Mode::Optimal).unwrap();
+            assert_eq!(data, compression::decompress(&fast).unwrap());
+            assert_eq!(data, compression::decompress(&optimal).unwrap());
+
+            println!(
+                "{:<20} {:>8} {:>8} {:>8}",
+                name,
+                vanilla,
+                fast.len(),
+                optimal.len()
+            );
+            totals[0] += vanilla;
+            totals[1] += fast.len();
+            totals[2] += optimal.len();
+        }
+        println!(
+            "{:<20} {:>8} {:>8} {:>8}",
+            "total", totals[0], totals[1], totals[2]
+        );
+    }
 }
 
 fn main() -> std::io::Result<()> {
-    let rom = Rom::new()?;
+    let mut rom = Rom::new()?;
+    let args: Vec<String> = env::args().collect();
 
-    rom.parse_locations();
+    match args.get(1).map(|s| s.as_str()) {
+        Some("compression-bench") => rom.compression_bench(),
+        Some("render") => rom.render_locations(false, false),
+        Some("render-annotated") => {
+            rom.render_locations(true, args.get(2).map(|s| s.as_str()) == Some("outline"))
+        }
+        Some("collision") => rom.check_collision(),
+        Some("palettes") => rom.export_palettes(),
+        Some("palette-import") if args.len() == 5 => {
+            rom.import_palette(&args[2], &args[3], &args[4])
+        }
+        Some("monsters") => rom.export_monsters(),
+        Some("monster-ai") => rom.export_monster_ai(),
+        Some("monster-sprites") => rom.export_monster_sprites(),
+        Some("items") => rom.export_items(),
+        Some("characters") => rom.export_characters(),
+        Some("shops") => rom.export_shops(),
+        Some("shop-import") if args.len() == 3 => rom.import_shops(&args[2]),
+        Some("character-import") if args.len() == 3 => rom.import_characters(&args[2]),
+        Some("project") if args.len() == 4 => rom.print_projection(&args[2], &args[3]),
+        Some("magic") => rom.export_magic(),
+        Some("abilities") => rom.export_abilities(),
+        Some("magic-import") if args.len() == 3 => rom.import_magic(&args[2]),
+        Some("item-import") if args.len() == 3 => rom.import_items(&args[2]),
+        Some("encounters") => rom.export_encounters(),
+        Some("world") => rom.render_world_maps(),
+        Some("battle-backgrounds") => rom.render_battle_backgrounds(),
+        Some("formations") => rom.export_formations(),
+        Some("formation-import") if args.len() == 3 => rom.import_formations(&args[2]),
+        Some("graph") => rom.export_graph(args.get(2).map(|s| s.as_str()).unwrap_or("0")),
+        Some("route") if args.len() == 4 => rom.print_route(&args[2], &args[3]),
+        Some("location-import") if args.len() == 3 => rom.import_locations(&args[2]),
+        Some("music") => rom.export_music(),
+        Some("fonts") => rom.export_fonts(),
+        Some("font-import") if args.len() == 3 => rom.import_fonts(&args[2]),
+        Some("sprites") => rom.export_sprites(),
+        Some("tmx") => rom.export_tmx(),
+        Some("tmx-import") if args.len() == 4 => rom.import_tmx(&args[2], &args[3]),
+        Some("sprite-import") if args.len() == 4 => rom.import_sprite(&args[2], &args[3]),
+        _ => rom.parse_locations(),
+    }
     Ok(())
 }
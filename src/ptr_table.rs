@@ -1,6 +1,8 @@
 use simple_error::SimpleError;
 use std::error::Error;
 
+use utils::check_len;
+
 #[derive(Debug, PartialEq)]
 pub struct Entry {
     pub addr: usize,
@@ -32,6 +34,10 @@ fn decode_u16(data: &[u8]) -> u16 {
     data[0] as u16 | ((data[1] as u16) << 8)
 }
 
+fn decode_u24(data: &[u8]) -> u32 {
+    data[0] as u32 | ((data[1] as u32) << 8) | ((data[2] as u32) << 16)
+}
+
 impl Table {
     pub fn new(data: &[u8], entries: usize, offset: usize) -> Table {
         let mut table = Table {
@@ -50,6 +56,67 @@ impl Table {
 
         table
     }
+
+    // Some tables have 24 bit pointers and are not sorted.  Entries which
+    // are out of order get a len of 0.
+    pub fn new_u24(data: &[u8], entries: usize, offset: usize) -> Table {
+        let mut table = Table {
+            entries: Vec::new(),
+        };
+        // The last entry exists only for sizing the previous range.
+        for i in 0..(entries - 1) {
+            let addr = decode_u24(&data[i * 3..]);
+            let next_addr = decode_u24(&data[(i + 1) * 3..]);
+            table.entries.push(Entry {
+                addr: offset + addr as usize,
+                len: next_addr.saturating_sub(addr) as usize,
+            });
+        }
+
+        table
+    }
+}
+
+// Rewrites a table of 16 bit offsets from `base` along with the data it
+// points to.  The table has one more pointer than `entries` which marks the
+// end of the region and the data starts right after the table.  That pointer
+// is left alone and the data is packed against it so space freed by a
+// smaller write is filled with 0xff at the start of the region and is still
+// available to later writes.
+pub fn write_u16(
+    data: &mut [u8],
+    table_addr: usize,
+    base: usize,
+    entries: &[Vec<u8>],
+) -> Result<(), Box<Error>> {
+    let last = table_addr + entries.len() * 2;
+    check_len(data, last, 2)?;
+    let start = (last + 2).saturating_sub(base);
+    let end = decode_u16(&data[last..]) as usize;
+    let len: usize = entries.iter().map(|e| e.len()).sum();
+    if start + len > end {
+        return Err(SimpleError::new(format!(
+            "table data is 0x{:x} bytes.  Only 0x{:x} available.",
+            len,
+            end.saturating_sub(start)
+        ))
+        .into());
+    }
+    if data.len() < base + end {
+        return Err(SimpleError::new("table points past end of data").into());
+    }
+
+    for b in data[base + start..base + end - len].iter_mut() {
+        *b = 0xff;
+    }
+    let mut offset = end - len;
+    for (i, e) in entries.iter().enumerate() {
+        data[table_addr + i * 2] = offset as u8;
+        data[table_addr + i * 2 + 1] = (offset >> 8) as u8;
+        data[base + offset..base + offset + e.len()].copy_from_slice(e);
+        offset += e.len();
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -82,4 +149,52 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn u24_test() {
+        assert_eq!(
+            Table {
+                entries: vec!(
+                    Entry {
+                        addr: 0x123456,
+                        len: 0x10000
+                    },
+                    Entry {
+                        addr: 0x133456,
+                        len: 0
+                    },
+                )
+            },
+            Table::new_u24(
+                &[0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+                3,
+                0x123456
+            )
+        );
+    }
+
+    #[test]
+    fn write_test() {
+        let mut data = vec![0x06, 0x00, 0x08, 0x00, 0x0b, 0x00, 0, 0, 0, 0, 0, 0];
+        write_u16(&mut data, 0, 0, &[vec![1], vec![2, 3]]).unwrap();
+        assert_eq!(
+            vec![0x08, 0x00, 0x09, 0x00, 0x0b, 0x00, 0xff, 0xff, 1, 2, 3, 0],
+            data
+        );
+        let table = Table::new(&data, 3, 0);
+        assert_eq!(2, table.entries[1].len);
+
+        // The space freed above is still available.
+        write_u16(&mut data, 0, 0, &[vec![1, 2], vec![3, 4, 5]]).unwrap();
+        assert_eq!(
+            vec![0x06, 0x00, 0x08, 0x00, 0x0b, 0x00, 1, 2, 3, 4, 5, 0],
+            data
+        );
+        assert!(write_u16(&mut data, 0, 0, &[vec![1, 2], vec![3, 4, 5, 6]]).is_err());
+
+        // Data offsets are relative to `base`.
+        let mut data = vec![0x00, 0x00, 0x02, 0x00, 0, 0];
+        write_u16(&mut data, 0, 4, &[vec![7]]).unwrap();
+        assert_eq!(vec![0x01, 0x00, 0x02, 0x00, 0xff, 7], data);
+    }
 }
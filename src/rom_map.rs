@@ -3,6 +3,119 @@ pub const NPC_POINTERS: usize = 0xc41a10;
 pub const NPC_DATA: usize = 0xc41d52;
 pub const LOCATION_PROPERTIES: usize = 0xed8f00;
 pub const LOCATION_ENTRANCE_EVENTS: usize = 0xd1fa00;
+pub const EVENT_SCRIPTS: usize = 0xca0000;
+pub const MAP_TILE_PROPERTIES_POINTERS: usize = 0xd9a510;
+pub const MAP_TILE_PROPERTIES: usize = 0xd90000;
+pub const MAP_TILEMAP_POINTERS: usize = 0xd9cd10;
+pub const MAP_TILEMAPS: usize = 0xd9d1b0;
+pub const MAP_TILESET_POINTERS: usize = 0xdfba00;
+pub const MAP_TILESETS: usize = 0xde0000;
+pub const MAP_GRAPHICS_POINTERS: usize = 0xdfda00;
+pub const MAP_GRAPHICS: usize = 0xe72010;
+pub const MAP_PALETTES: usize = 0xedc480;
+pub const SPRITE_PALETTES: usize = 0xed6200;
+pub const BATTLE_BG_PALETTES: usize = 0xe70270;
+pub const BATTLE_BG_DATA: usize = 0xe70150;
+pub const BATTLE_BG_GRAPHICS_POINTERS: usize = 0xe71650;
+pub const BATTLE_BG_TILEMAP_POINTERS: usize = 0xe71848;
+pub const BATTLE_BG_TILEMAP_BANK: usize = 0xe70000;
+pub const MONSTER_PALETTES: usize = 0xd27820;
+pub const MONSTER_GRAPHICS: usize = 0xe97000;
+pub const MONSTER_GRAPHICS_INFO: usize = 0xd27000;
+pub const MONSTER_SMALL_MASK_POINTER: usize = 0xd2a820;
+pub const MONSTER_LARGE_MASK_POINTER: usize = 0xd2a822;
+pub const MONSTER_MASK_BANK: usize = 0xd20000;
+pub const SMALL_FONT_GRAPHICS: usize = 0xc487c0;
+pub const DIALOG_FONT_WIDTHS: usize = 0xc48fc0;
+pub const DIALOG_FONT_GRAPHICS: usize = 0xc490c0;
+pub const SPC_ENGINE: usize = 0xc5070e;
+pub const SAMPLE_POINTERS: usize = 0xc53c5f;
+pub const SAMPLE_LOOP_POINTS: usize = 0xc53d1c;
+pub const SAMPLE_PITCHES: usize = 0xc53d9a;
+pub const SAMPLE_ADSR: usize = 0xc53e18;
+pub const SONG_POINTERS: usize = 0xc53e96;
+pub const SONG_INSTRUMENTS: usize = 0xc53f95;
+pub const SPRITE_POSE_LAYOUTS: usize = 0xc0ce3a;
+pub const SPRITE_GRAPHICS_POINTERS: usize = 0xc0d13a;
+pub const SPRITE_GRAPHICS_BANKS: usize = 0xc0d284;
+pub const SPRITE_GRAPHICS_SIZES: usize = 0xc0d3ce;
+pub const MAP_BG3_GRAPHICS_POINTERS: usize = 0xe68780;
+pub const MAP_BG3_GRAPHICS: usize = 0xe68780;
+pub const SHORT_EXIT_POINTERS: usize = 0xdfbb00;
+pub const LONG_EXIT_POINTERS: usize = 0xedf480;
+pub const TREASURE_POINTERS: usize = 0xed82f4;
+pub const TREASURE_DATA: usize = 0xed8634;
+pub const FORMATION_PACKS: usize = 0xcf4800;
+pub const WORLD_ENCOUNTER_PACKS: usize = 0xcf5000;
+pub const LOCATION_ENCOUNTER_PACKS: usize = 0xcf5400;
+pub const WORLD_ENCOUNTER_RATES: usize = 0xcf5800;
+pub const LOCATION_ENCOUNTER_RATES: usize = 0xcf5880;
+pub const MONSTER_DATA: usize = 0xcf0000;
+pub const MONSTER_ITEMS: usize = 0xcf3000;
+pub const MONSTER_NAMES: usize = 0xcfc050;
+pub const MONSTER_SPECIAL_NAMES: usize = 0xcfd0d0;
+pub const MONSTER_AI_POINTERS: usize = 0xcf8400;
+pub const MONSTER_AI_SCRIPTS: usize = 0xcf8700;
+pub const FORMATIONS: usize = 0xcf6200;
+pub const FORMATION_AUX: usize = 0xcf5900;
+pub const ITEM_DATA: usize = 0xd85000;
+pub const ITEM_NAMES: usize = 0xd2b300;
+pub const SPELL_DATA: usize = 0xc46ac0;
+pub const MAGIC_NAMES: usize = 0xe6f567;
+pub const ESPER_NAMES: usize = 0xe6f6e1;
+pub const ATTACK_NAMES: usize = 0xe6f7b9;
+pub const ESPER_DATA: usize = 0xd86e00;
+pub const TERRA_NATURAL_MAGIC: usize = 0xece3c0;
+pub const CELES_NATURAL_MAGIC: usize = 0xece3e0;
+pub const CHARACTER_DATA: usize = 0xed7ca0;
+pub const CHARACTER_NAMES: usize = 0xc478c0;
+pub const LEVEL_UP_HP: usize = 0xe6f4a0;
+pub const LEVEL_UP_MP: usize = 0xe6f502;
+pub const EXPERIENCE_TABLE: usize = 0xed8220;
+pub const SHOP_DATA: usize = 0xc47ac0;
+pub const BLITZ_CODES: usize = 0xc47a40;
+pub const SWDTECH_NAMES: usize = 0xcf3c40;
+pub const RAGE_ATTACKS: usize = 0xcf4600;
+pub const DANCE_ATTACKS: usize = 0xcffe80;
+pub const DANCE_NAMES: usize = 0xe6ff9d;
+pub const BATTLE_BG_DANCES: usize = 0xed8e5b;
+pub const SLOT_ATTACKS: usize = 0xc24e4a;
+pub const WOB_TILEMAP: usize = 0xeed434;
+pub const WOB_GRAPHICS: usize = 0xeeb290;
+pub const WOB_TILE_PROPERTIES: usize = 0xee9b14;
+pub const WOB_PALETTE: usize = 0xd2ec00;
+pub const WOR_TILEMAP: usize = 0xef114f;
+pub const WOR_GRAPHICS: usize = 0xef6a56;
+pub const WOR_TILE_PROPERTIES: usize = 0xee9d14;
+pub const WOR_PALETTE: usize = 0xd2ed00;
+pub const SERPENT_TRENCH_TILEMAP: usize = 0xef9d17;
+pub const SERPENT_TRENCH_GRAPHICS: usize = 0xefb631;
+pub const SERPENT_TRENCH_PALETTE: usize = 0xd2ee00;
+
+pub const NUM_MAP_TILE_PROPERTIES: usize = 0x2b;
+pub const NUM_MAP_TILEMAPS: usize = 0x15f;
+pub const NUM_MAP_TILESETS: usize = 0x4b;
+pub const NUM_MAP_BG3_GRAPHICS: usize = 0x13;
+pub const NUM_SPRITES: usize = 0xa5;
+pub const NUM_MAP_PALETTES: usize = 0x30;
+pub const NUM_SPRITE_PALETTES: usize = 0x20;
+pub const NUM_BATTLE_BG_PALETTES: usize = 0x38;
+pub const NUM_BATTLE_BACKGROUNDS: usize = 0x38;
+pub const NUM_MONSTER_PALETTES: usize = 0x300;
+pub const NUM_SMALL_FONT_GLYPHS: usize = 0x80;
+pub const NUM_DIALOG_FONT_GLYPHS: usize = 0x80;
+pub const NUM_SONGS: usize = 0x55;
+pub const NUM_SAMPLES: usize = 0x3f;
+pub const NUM_FORMATION_PACKS: usize = 0x100;
+pub const NUM_LOCATIONS: usize = 0x19f;
+pub const NUM_WORLD_ZONES: usize = 0x40;
+pub const NUM_MONSTERS: usize = 0x180;
+pub const NUM_FORMATIONS: usize = 0x240;
+pub const NUM_ITEMS: usize = 0x100;
+pub const NUM_SPELLS: usize = 0x100;
+pub const NUM_ESPERS: usize = 0x1b;
+pub const NUM_CHARACTERS: usize = 0x40;
+pub const NUM_SHOPS: usize = 0x80;
 
 pub const SNES_ROM_ADDR: usize = 0xc00000;
 
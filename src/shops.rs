@@ -0,0 +1,203 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use items::{self, Item};
+use rom_map;
+use utils::check_len;
+
+const SHOP_SIZE: usize = 9;
+const NUM_SHOP_ITEMS: usize = 8;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Empty,
+    Weapon,
+    Armor,
+    Item,
+    Relic,
+    Vendor,
+    Unknown(u8),
+}
+
+impl Kind {
+    fn from_bits(b: u8) -> Kind {
+        match b & 0x7 {
+            0 => Kind::Empty,
+            1 => Kind::Weapon,
+            2 => Kind::Armor,
+            3 => Kind::Item,
+            4 => Kind::Relic,
+            5 => Kind::Vendor,
+            b => Kind::Unknown(b),
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Kind::Empty => 0,
+            Kind::Weapon => 1,
+            Kind::Armor => 2,
+            Kind::Item => 3,
+            Kind::Relic => 4,
+            Kind::Vendor => 5,
+            Kind::Unknown(b) => b & 0x7,
+        }
+    }
+
+    // Whether a shop of this kind can sell items of `kind`.  Weapon shops
+    // also sell Edgar's tools.  Vendors and shops of an unknown kind sell
+    // anything and empty shops sell nothing.
+    pub fn sells(self, kind: items::Kind) -> bool {
+        match self {
+            Kind::Empty => false,
+            Kind::Weapon => matches!(kind, items::Kind::Weapon | items::Kind::Tool),
+            Kind::Armor => matches!(
+                kind,
+                items::Kind::Armor | items::Kind::Shield | items::Kind::Helmet
+            ),
+            Kind::Item => kind == items::Kind::Item,
+            Kind::Relic => kind == items::Kind::Relic,
+            Kind::Vendor | Kind::Unknown(_) => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Shop {
+    pub kind: Kind,
+    // Index into the shop price adjustment table.
+    pub price_modifier: u8,
+    pub unknown_bits: u8,
+    // Up to 8 items.  Empty slots are dropped.
+    pub items: Vec<u8>,
+}
+
+impl Shop {
+    pub fn item_names(&self, items: &[Item]) -> Vec<String> {
+        self.items.iter().map(|i| items::name(items, *i)).collect()
+    }
+
+    // Checks the item count and that every item can be sold by this kind of
+    // shop.
+    pub fn validate(&self, items: &[Item]) -> Result<(), Box<Error>> {
+        if self.items.len() > NUM_SHOP_ITEMS {
+            return Err(SimpleError::new(format!(
+                "shops sell at most {} items.  Shop has {}.",
+                NUM_SHOP_ITEMS,
+                self.items.len()
+            ))
+            .into());
+        }
+        for i in &self.items {
+            let item = items
+                .get(*i as usize)
+                .ok_or_else(|| SimpleError::new(format!("unknown item {:02x}", i)))?;
+            if !self.kind.sells(item.kind) {
+                return Err(SimpleError::new(format!(
+                    "{:?} shop can't sell {:?} {}",
+                    self.kind, item.kind, item.name
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_shop(data: &[u8]) -> Result<Shop, Box<Error>> {
+    if data.len() < SHOP_SIZE {
+        return Err(SimpleError::new("shop data too short").into());
+    }
+    Ok(Shop {
+        kind: Kind::from_bits(data[0]),
+        price_modifier: (data[0] >> 3) & 0x7,
+        unknown_bits: data[0] >> 6,
+        items: data[1..SHOP_SIZE]
+            .iter()
+            .cloned()
+            .filter(|i| *i != items::EMPTY)
+            .collect(),
+    })
+}
+
+pub fn encode_shop(shop: &Shop) -> Vec<u8> {
+    let mut data =
+        vec![shop.unknown_bits << 6 | (shop.price_modifier & 0x7) << 3 | shop.kind.bits()];
+    data.extend(shop.items.iter().take(NUM_SHOP_ITEMS));
+    data.resize(SHOP_SIZE, items::EMPTY);
+    data
+}
+
+fn table_addr(rom_data: &[u8]) -> Result<usize, Box<Error>> {
+    let addr = rom_map::snes_to_file(rom_map::SHOP_DATA);
+    check_len(rom_data, addr, rom_map::NUM_SHOPS * SHOP_SIZE)?;
+    Ok(addr)
+}
+
+pub fn parse(rom_data: &[u8]) -> Result<Vec<Shop>, Box<Error>> {
+    let addr = table_addr(rom_data)?;
+    let mut shops = Vec::new();
+    for i in 0..rom_map::NUM_SHOPS {
+        shops.push(parse_shop(&rom_data[addr + i * SHOP_SIZE..])?);
+    }
+    Ok(shops)
+}
+
+// Shops are validated against `items` before anything is written.
+pub fn write(rom_data: &mut [u8], shops: &[Shop], items: &[Item]) -> Result<(), Box<Error>> {
+    if shops.len() != rom_map::NUM_SHOPS {
+        return Err(SimpleError::new(format!(
+            "expected {} shops.  Got {}.",
+            rom_map::NUM_SHOPS,
+            shops.len()
+        ))
+        .into());
+    }
+    for (i, s) in shops.iter().enumerate() {
+        s.validate(items)
+            .map_err(|e| SimpleError::new(format!("shop {:02x}: {}", i, e)))?;
+    }
+    let addr = table_addr(rom_data)?;
+    for (i, s) in shops.iter().enumerate() {
+        let start = addr + i * SHOP_SIZE;
+        rom_data[start..start + SHOP_SIZE].copy_from_slice(&encode_shop(s));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shop_test() {
+        let data = [0x0b, 0x00, 0x01, 0x02, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let shop = parse_shop(&data).unwrap();
+        assert_eq!(Kind::Item, shop.kind);
+        assert_eq!(1, shop.price_modifier);
+        assert_eq!(vec![0, 1, 2], shop.items);
+        assert_eq!(data.to_vec(), encode_shop(&shop));
+
+        assert!(Kind::Armor.sells(items::Kind::Helmet));
+        assert!(Kind::Weapon.sells(items::Kind::Tool));
+        assert!(!Kind::Weapon.sells(items::Kind::Relic));
+        assert!(Kind::Vendor.sells(items::Kind::Relic));
+        assert!(!Kind::Empty.sells(items::Kind::Item));
+        assert!(!Kind::Item.sells(items::Kind::Tool));
+    }
+
+    // Every shop in the original game must pass validation.  Needs the ROM
+    // that main loads.  Run with `cargo test -- --ignored` from the directory
+    // holding ff3.sfc.
+    #[test]
+    #[ignore]
+    fn vanilla_test() {
+        let rom_data = std::fs::read("ff3.sfc").unwrap();
+        let items = items::parse(&rom_data).unwrap();
+        for (i, s) in parse(&rom_data).unwrap().iter().enumerate() {
+            if let Err(e) = s.validate(&items) {
+                panic!("shop {:02x}: {}", i, e);
+            }
+        }
+    }
+}
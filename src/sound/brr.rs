@@ -0,0 +1,96 @@
+// BRR samples are 9 byte blocks: a header byte followed by 16 4 bit samples,
+// high nibble first.  The header holds the shift, the prediction filter,
+// and the loop and end flags.
+pub const BLOCK_LEN: usize = 9;
+pub const SAMPLES_PER_BLOCK: usize = 16;
+// The DSP plays samples at 32kHz when their pitch is 0x1000.
+pub const SAMPLE_RATE: u32 = 32000;
+
+// Decodes blocks until one with the end flag set.  Output is scaled to the
+// full 16 bit range.
+pub fn decode(data: &[u8]) -> Vec<i16> {
+    let mut samples = Vec::new();
+    let (mut p1, mut p2) = (0i32, 0i32);
+    for block in data.chunks(BLOCK_LEN) {
+        if block.len() < BLOCK_LEN {
+            break;
+        }
+        let header = block[0];
+        let shift = header >> 4;
+        let filter = (header >> 2) & 0x3;
+        for i in 0..SAMPLES_PER_BLOCK {
+            let b = block[1 + i / 2];
+            let nibble = if i % 2 == 0 { b >> 4 } else { b & 0xf };
+            // Sign extend.
+            let mut s = ((nibble << 4) as i8 >> 4) as i32;
+            s = if shift <= 12 {
+                (s << shift) >> 1
+            } else if s < 0 {
+                -0x800
+            } else {
+                0
+            };
+            s += match filter {
+                1 => p1 + ((-p1) >> 4),
+                2 => (p1 << 1) + ((-p1 * 3) >> 5) - p2 + (p2 >> 4),
+                3 => (p1 << 1) + ((-p1 * 13) >> 6) - p2 + ((p2 * 3) >> 4),
+                _ => 0,
+            };
+            // The DSP keeps 15 bits of output.
+            s = ((s.clamp(-0x8000, 0x7fff) as i16) << 1) as i32 >> 1;
+            p2 = p1;
+            p1 = s;
+            samples.push((s << 1) as i16);
+        }
+        if header & 0x1 == 0x1 {
+            break;
+        }
+    }
+    samples
+}
+
+// 16 bit mono PCM wave file.
+pub fn to_wav(samples: &[i16], rate: u32) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut wav = Vec::new();
+    wav.extend(b"RIFF");
+    wav.extend(&(36 + data_len).to_le_bytes());
+    wav.extend(b"WAVEfmt ");
+    wav.extend(&16u32.to_le_bytes());
+    // PCM, one channel.
+    wav.extend(&1u16.to_le_bytes());
+    wav.extend(&1u16.to_le_bytes());
+    wav.extend(&rate.to_le_bytes());
+    wav.extend(&(rate * 2).to_le_bytes());
+    wav.extend(&2u16.to_le_bytes());
+    wav.extend(&16u16.to_le_bytes());
+    wav.extend(b"data");
+    wav.extend(&data_len.to_le_bytes());
+    for s in samples {
+        wav.extend(&s.to_le_bytes());
+    }
+    wav
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_test() {
+        // Shift 12, no filter: 0x7 and 0xf decode to 7 << 11 and -1 << 11.
+        let mut data = vec![0xc0, 0x7f, 0, 0, 0, 0, 0, 0, 0];
+        data.extend(&[0xc1, 0x10, 0, 0, 0, 0, 0, 0, 0]);
+        data.extend(&[0xc0, 0x10, 0, 0, 0, 0, 0, 0, 0]);
+        let samples = decode(&data);
+        assert_eq!(32, samples.len());
+        assert_eq!(7 << 12, samples[0]);
+        assert_eq!(-1 << 12, samples[1]);
+        assert_eq!(1 << 12, samples[16]);
+
+        let wav = to_wav(&samples, SAMPLE_RATE);
+        assert_eq!(44 + 64, wav.len());
+        assert_eq!(b"RIFF", &wav[0..4]);
+        assert_eq!(&[0x00, 0x70], &wav[44..46]);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod brr;
+pub mod song;
+pub mod spc;
@@ -0,0 +1,177 @@
+use simple_error::SimpleError;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use field::Location;
+use rom_map;
+use utils::check_len;
+
+// Only songs are handled here.  Sound effects have their own sequence and
+// sample tables which aren't parsed or exported.
+pub const NUM_CHANNELS: usize = 8;
+pub const NUM_SONG_INSTRUMENTS: usize = 16;
+// Instrument slots without a sample.
+pub const NO_SAMPLE: u16 = 0;
+// The start and end addresses of the sequence followed by each channel's
+// start address and a second set of channel addresses.
+pub const SONG_HEADER_SIZE: usize = 4 + NUM_CHANNELS * 4;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Sample {
+    pub brr: Vec<u8>,
+    // Byte offset into `brr` where looping samples restart.
+    pub loop_point: u16,
+    pub pitch: u16,
+    pub adsr: u16,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Song {
+    // Sequence data as loaded into SPC RAM.
+    pub data: Vec<u8>,
+    // One based sample numbers for each of the song's instrument slots.
+    pub instruments: [u16; NUM_SONG_INSTRUMENTS],
+}
+
+impl Song {
+    // The start address of each channel before the song is relocated.
+    // Unused channels point past the end of the song.
+    pub fn channels(&self) -> [u16; NUM_CHANNELS] {
+        let mut channels = [0u16; NUM_CHANNELS];
+        for (i, c) in channels.iter_mut().enumerate() {
+            let o = 4 + i * 2;
+            if let Some(w) = self.data.get(o..o + 2) {
+                *c = w[0] as u16 | (w[1] as u16) << 8;
+            }
+        }
+        channels
+    }
+
+    pub fn samples(&self) -> Vec<usize> {
+        self.instruments
+            .iter()
+            .filter(|i| **i != NO_SAMPLE)
+            .map(|i| *i as usize - 1)
+            .collect()
+    }
+}
+
+fn word(rom_data: &[u8], addr: usize) -> u16 {
+    rom_data[addr] as u16 | (rom_data[addr + 1] as u16) << 8
+}
+
+// Songs, samples, and the engine are stored as a 16 bit length followed by
+// that many bytes.
+fn blob(rom_data: &[u8], addr: usize) -> Result<Vec<u8>, Box<Error>> {
+    check_len(rom_data, addr, 2)?;
+    let len = word(rom_data, addr) as usize;
+    check_len(rom_data, addr + 2, len)?;
+    Ok(rom_data[addr + 2..addr + 2 + len].to_vec())
+}
+
+// Follows the 24 bit pointer at `index` in the table at `table_addr`.
+fn long_ptr(rom_data: &[u8], table_addr: usize, index: usize) -> Result<usize, Box<Error>> {
+    let ptr = rom_map::snes_to_file(table_addr) + index * 3;
+    check_len(rom_data, ptr, 3)?;
+    let snes_addr = rom_data[ptr] as usize
+        | (rom_data[ptr + 1] as usize) << 8
+        | (rom_data[ptr + 2] as usize) << 16;
+    if snes_addr < rom_map::SNES_ROM_ADDR {
+        return Err(SimpleError::new(format!(
+            "bad pointer {:06x} at {:06x}",
+            snes_addr, table_addr
+        ))
+        .into());
+    }
+    Ok(rom_map::snes_to_file(snes_addr))
+}
+
+// SPC700 code the main CPU uploads to the sound CPU at boot.
+pub fn engine(rom_data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+    blob(rom_data, rom_map::snes_to_file(rom_map::SPC_ENGINE))
+}
+
+pub fn parse_samples(rom_data: &[u8]) -> Result<Vec<Sample>, Box<Error>> {
+    let n = rom_map::NUM_SAMPLES;
+    let loops = rom_map::snes_to_file(rom_map::SAMPLE_LOOP_POINTS);
+    let pitches = rom_map::snes_to_file(rom_map::SAMPLE_PITCHES);
+    let adsr = rom_map::snes_to_file(rom_map::SAMPLE_ADSR);
+    check_len(rom_data, loops, n * 2)?;
+    check_len(rom_data, pitches, n * 2)?;
+    check_len(rom_data, adsr, n * 2)?;
+    let mut samples = Vec::new();
+    for i in 0..n {
+        samples.push(Sample {
+            brr: blob(rom_data, long_ptr(rom_data, rom_map::SAMPLE_POINTERS, i)?)?,
+            loop_point: word(rom_data, loops + i * 2),
+            pitch: word(rom_data, pitches + i * 2),
+            adsr: word(rom_data, adsr + i * 2),
+        });
+    }
+    Ok(samples)
+}
+
+pub fn parse_song(rom_data: &[u8], index: usize) -> Result<Song, Box<Error>> {
+    let instruments = rom_map::snes_to_file(rom_map::SONG_INSTRUMENTS);
+    check_len(
+        rom_data,
+        instruments,
+        rom_map::NUM_SONGS * NUM_SONG_INSTRUMENTS * 2,
+    )?;
+    let mut song = Song {
+        data: blob(rom_data, long_ptr(rom_data, rom_map::SONG_POINTERS, index)?)?,
+        instruments: [NO_SAMPLE; NUM_SONG_INSTRUMENTS],
+    };
+    for (j, s) in song.instruments.iter_mut().enumerate() {
+        *s = word(
+            rom_data,
+            instruments + (index * NUM_SONG_INSTRUMENTS + j) * 2,
+        );
+    }
+    Ok(song)
+}
+
+// Each song is parsed on its own so one bad pointer doesn't hide the rest.
+pub fn parse_songs(rom_data: &[u8]) -> Vec<Result<Song, Box<Error>>> {
+    (0..rom_map::NUM_SONGS)
+        .map(|i| parse_song(rom_data, i))
+        .collect()
+}
+
+// Maps each song to the locations which play it.
+pub fn locations_by_song(locations: &[Location]) -> BTreeMap<u8, Vec<usize>> {
+    let mut users: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    for (l, location) in locations.iter().enumerate() {
+        users
+            .entry(location.properties.music_track)
+            .or_default()
+            .push(l);
+    }
+    users
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn song_test() {
+        let mut rom = vec![0u8; 0x400000];
+        let ptr = rom_map::snes_to_file(rom_map::SONG_POINTERS) + 3;
+        rom[ptr..ptr + 3].copy_from_slice(&[0x00, 0x00, 0xc6]);
+        let song = rom_map::snes_to_file(0xc60000);
+        rom[song..song + 8].copy_from_slice(&[0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x34, 0x12]);
+        let instruments = rom_map::snes_to_file(rom_map::SONG_INSTRUMENTS);
+        rom[instruments + 32] = 0x05;
+
+        // Song 0 has a null pointer.
+        let songs = parse_songs(&rom);
+        assert_eq!(rom_map::NUM_SONGS, songs.len());
+        assert!(songs[0].is_err());
+        let song = songs[1].as_ref().unwrap();
+        assert_eq!(6, song.data.len());
+        assert_eq!(0x1234, song.channels()[0]);
+        assert_eq!(0, song.channels()[1]);
+        assert_eq!(vec![4], song.samples());
+    }
+}
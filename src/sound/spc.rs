@@ -0,0 +1,192 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use sound::song::{Sample, Song, NO_SAMPLE, SONG_HEADER_SIZE};
+use utils::get_u16;
+
+// Sound RAM layout the engine expects once the main CPU has transferred a
+// song: code at 0x0200, per instrument pitch and ADSR tables, the DSP sample
+// directory, the song's length word and sequence data, and then BRR samples
+// up to the echo buffer.
+const ENGINE_ADDR: usize = 0x0200;
+const PITCH_TABLE: usize = 0x1a00;
+const ADSR_TABLE: usize = 0x1a40;
+const SAMPLE_DIRECTORY: usize = 0x1b00;
+const SONG_ADDR: usize = 0x1c00;
+const SAMPLE_ADDR: usize = 0x4800;
+const SAMPLE_END: usize = 0xf600;
+
+const RAM_SIZE: usize = 0x10000;
+const HEADER_SIZE: usize = 0x100;
+const DSP_REGS: usize = HEADER_SIZE + RAM_SIZE;
+const FILE_SIZE: usize = DSP_REGS + 0x100;
+
+// DSP registers.
+const DSP_MAIN_VOLUME_L: usize = 0x0c;
+const DSP_MAIN_VOLUME_R: usize = 0x1c;
+const DSP_ECHO_VOLUME_L: usize = 0x2c;
+const DSP_ECHO_VOLUME_R: usize = 0x3c;
+const DSP_KEY_OFF: usize = 0x5c;
+const DSP_DIR: usize = 0x5d;
+const DSP_FLAGS: usize = 0x6c;
+const DSP_ECHO_ON: usize = 0x4d;
+const DSP_ECHO_START: usize = 0x6d;
+const DSP_ECHO_DELAY: usize = 0x7d;
+
+fn too_big(what: &str, end: usize, limit: usize) -> Box<Error> {
+    SimpleError::new(format!(
+        "{} ends at {:04x}.  Must be before {:04x}.",
+        what, end, limit
+    ))
+    .into()
+}
+
+fn put_str(data: &mut [u8], offset: usize, len: usize, s: &str) {
+    for (i, b) in s.bytes().take(len).enumerate() {
+        data[offset + i] = b;
+    }
+}
+
+// Rebases the header pointers of a song's sequence data onto SONG_ADDR.  The
+// first header word is the address the song's pointers assume its sequence
+// starts at, just past the header.  The engine loads the song's length word
+// and header ahead of the sequence.
+pub fn relocate(data: &[u8]) -> Result<Vec<u8>, Box<Error>> {
+    if data.len() < SONG_HEADER_SIZE {
+        return Err(SimpleError::new("song shorter than its header").into());
+    }
+    let start = (SONG_ADDR + 2 + SONG_HEADER_SIZE) as u16;
+    let delta = start.wrapping_sub(get_u16(data));
+    let mut data = data.to_vec();
+    for o in (0..SONG_HEADER_SIZE).step_by(2) {
+        let ptr = get_u16(&data[o..]).wrapping_add(delta);
+        data[o] = ptr as u8;
+        data[o + 1] = (ptr >> 8) as u8;
+    }
+    Ok(data)
+}
+
+// Builds a SPC file holding the engine, `song`, and its samples where the
+// engine keeps them after receiving a song, with the DSP set up for the
+// sample directory and echo off.  The saved PC is the engine's entry point,
+// not its state after a transfer, so the song isn't started until the
+// engine is sent a play command.
+pub fn build(
+    engine: &[u8],
+    song: &Song,
+    index: usize,
+    samples: &[Sample],
+) -> Result<Vec<u8>, Box<Error>> {
+    let mut spc = vec![0u8; FILE_SIZE];
+    put_str(&mut spc, 0, 33, "SNES-SPC700 Sound File Data v0.30");
+    spc[0x21] = 26;
+    spc[0x22] = 26;
+    spc[0x23] = 26;
+    spc[0x24] = 30;
+    spc[0x25] = ENGINE_ADDR as u8;
+    spc[0x26] = (ENGINE_ADDR >> 8) as u8;
+    // Stack pointer.
+    spc[0x2b] = 0xff;
+    put_str(&mut spc, 0x2e, 32, &format!("Song {:02x}", index));
+    put_str(&mut spc, 0x4e, 32, "Final Fantasy III");
+
+    let ram = &mut spc[HEADER_SIZE..HEADER_SIZE + RAM_SIZE];
+    if ENGINE_ADDR + engine.len() > PITCH_TABLE {
+        return Err(too_big("engine", ENGINE_ADDR + engine.len(), PITCH_TABLE));
+    }
+    ram[ENGINE_ADDR..ENGINE_ADDR + engine.len()].copy_from_slice(engine);
+    let data = relocate(&song.data)?;
+    let end = SONG_ADDR + 2 + data.len();
+    if end > SAMPLE_ADDR {
+        return Err(too_big("song", end, SAMPLE_ADDR));
+    }
+    ram[SONG_ADDR] = data.len() as u8;
+    ram[SONG_ADDR + 1] = (data.len() >> 8) as u8;
+    ram[SONG_ADDR + 2..end].copy_from_slice(&data);
+
+    // Directory entries are the start and loop address of each instrument's
+    // sample.
+    let mut addr = SAMPLE_ADDR;
+    for (slot, s) in song.instruments.iter().enumerate() {
+        if *s == NO_SAMPLE {
+            continue;
+        }
+        let sample = samples
+            .get(*s as usize - 1)
+            .ok_or_else(|| SimpleError::new(format!("unknown sample {:02x}", s)))?;
+        let end = addr + sample.brr.len();
+        if end > SAMPLE_END {
+            return Err(too_big("samples", end, SAMPLE_END));
+        }
+        ram[addr..end].copy_from_slice(&sample.brr);
+        let loop_addr = addr + sample.loop_point as usize;
+        let dir = SAMPLE_DIRECTORY + slot * 4;
+        ram[dir] = addr as u8;
+        ram[dir + 1] = (addr >> 8) as u8;
+        ram[dir + 2] = loop_addr as u8;
+        ram[dir + 3] = (loop_addr >> 8) as u8;
+        ram[PITCH_TABLE + slot * 2] = sample.pitch as u8;
+        ram[PITCH_TABLE + slot * 2 + 1] = (sample.pitch >> 8) as u8;
+        ram[ADSR_TABLE + slot * 2] = sample.adsr as u8;
+        ram[ADSR_TABLE + slot * 2 + 1] = (sample.adsr >> 8) as u8;
+        addr = end;
+    }
+
+    let dsp = &mut spc[DSP_REGS..DSP_REGS + 0x80];
+    dsp[DSP_MAIN_VOLUME_L] = 0x7f;
+    dsp[DSP_MAIN_VOLUME_R] = 0x7f;
+    dsp[DSP_ECHO_VOLUME_L] = 0;
+    dsp[DSP_ECHO_VOLUME_R] = 0;
+    dsp[DSP_KEY_OFF] = 0;
+    dsp[DSP_ECHO_ON] = 0;
+    dsp[DSP_ECHO_START] = (SAMPLE_END >> 8) as u8;
+    dsp[DSP_ECHO_DELAY] = 0;
+    // Mute off, echo writes disabled.
+    dsp[DSP_FLAGS] = 0x20;
+    dsp[DSP_DIR] = (SAMPLE_DIRECTORY >> 8) as u8;
+    Ok(spc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_test() {
+        // The song assumes its sequence starts at 0x2000.  Channel 0 starts
+        // there and channel 1 0x10 bytes in.
+        let mut data = vec![0u8; SONG_HEADER_SIZE];
+        data[0..6].copy_from_slice(&[0x00, 0x20, 0x20, 0x20, 0x00, 0x20]);
+        data[6..8].copy_from_slice(&[0x10, 0x20]);
+        data.extend_from_slice(&[0xaa; 0x20]);
+        let mut song = Song {
+            data: data,
+            instruments: [0; 16],
+        };
+        song.instruments[1] = 2;
+        let sample = Sample {
+            brr: vec![0x03; 18],
+            loop_point: 9,
+            pitch: 0x1234,
+            adsr: 0,
+        };
+        let samples = vec![sample.clone(), sample];
+        let spc = build(&[0x20, 0x5f], &song, 0x1f, &samples).unwrap();
+        assert_eq!(FILE_SIZE, spc.len());
+        assert_eq!(b"SNES-SPC700", &spc[0..11]);
+        let ram = &spc[HEADER_SIZE..];
+        assert_eq!([0x20, 0x5f], ram[0x200..0x202]);
+        assert_eq!([0x44, 0x00], ram[SONG_ADDR..SONG_ADDR + 2]);
+        assert_eq!([0x26, 0x1c, 0x46, 0x1c], ram[0x1c02..0x1c06]);
+        assert_eq!([0x26, 0x1c, 0x36, 0x1c], ram[0x1c06..0x1c0a]);
+        assert_eq!(0xaa, ram[0x1c26]);
+        assert_eq!([0x00, 0x48, 0x09, 0x48], ram[0x1b04..0x1b08]);
+        assert_eq!([0x34, 0x12], ram[0x1a02..0x1a04]);
+        assert_eq!(0x1b, spc[DSP_REGS + DSP_DIR]);
+
+        song.instruments[2] = 3;
+        assert!(build(&[], &song, 0, &samples).is_err());
+        song.data.truncate(4);
+        assert!(relocate(&song.data).is_err());
+    }
+}
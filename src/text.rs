@@ -0,0 +1,107 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+// Fixed length names in menus and battle use a single byte per character.
+// Unused space is padded with 0xff, which is also the space character.  0xfe
+// draws as a space too but is kept as {fe} so names round trip.
+const PAD: u8 = 0xff;
+
+const PUNCTUATION: [(u8, char); 16] = [
+    (0xbe, '!'),
+    (0xbf, '?'),
+    (0xc0, '/'),
+    (0xc1, ':'),
+    (0xc2, '"'),
+    (0xc3, '\''),
+    (0xc4, '-'),
+    (0xc5, '.'),
+    (0xc6, ','),
+    (0xc7, '…'),
+    (0xc8, ';'),
+    (0xc9, '#'),
+    (0xca, '+'),
+    (0xcb, '('),
+    (0xcc, ')'),
+    (0xcd, '%'),
+];
+
+fn decode_char(b: u8) -> Option<char> {
+    match b {
+        0x80..=0x99 => Some((b'A' + (b - 0x80)) as char),
+        0x9a..=0xb3 => Some((b'a' + (b - 0x9a)) as char),
+        0xb4..=0xbd => Some((b'0' + (b - 0xb4)) as char),
+        PAD => Some(' '),
+        _ => PUNCTUATION.iter().find(|p| p.0 == b).map(|p| p.1),
+    }
+}
+
+fn encode_char(c: char) -> Option<u8> {
+    match c {
+        'A'..='Z' => Some(0x80 + (c as u8 - b'A')),
+        'a'..='z' => Some(0x9a + (c as u8 - b'a')),
+        '0'..='9' => Some(0xb4 + (c as u8 - b'0')),
+        ' ' => Some(PAD),
+        _ => PUNCTUATION.iter().find(|p| p.1 == c).map(|p| p.0),
+    }
+}
+
+// Bytes without a character, such as item icons, are written as {xx}.
+// Trailing padding is removed.
+pub fn decode_name(data: &[u8]) -> String {
+    let mut name = String::new();
+    for b in data {
+        match decode_char(*b) {
+            Some(c) => name.push(c),
+            None => name.push_str(&format!("{{{:02x}}}", b)),
+        }
+    }
+    name.trim_end().to_string()
+}
+
+pub fn encode_name(name: &str, len: usize) -> Result<Vec<u8>, Box<Error>> {
+    let mut data = Vec::new();
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c == '{' {
+            let hex: String = chars.by_ref().take_while(|c| *c != '}').collect();
+            data.push(u8::from_str_radix(&hex, 16)?);
+            continue;
+        }
+        match encode_char(c) {
+            Some(b) => data.push(b),
+            None => {
+                return Err(
+                    SimpleError::new(format!("can't encode '{}' in \"{}\"", c, name)).into(),
+                )
+            }
+        }
+    }
+    if data.len() > len {
+        return Err(
+            SimpleError::new(format!("\"{}\" is longer than {} characters", name, len)).into(),
+        );
+    }
+    data.resize(len, PAD);
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_test() {
+        let data = [0x86, 0xae, 0x9a, 0xab, 0x9d, 0xff, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!("Guard", decode_name(&data));
+        assert_eq!(data.to_vec(), encode_name("Guard", 10).unwrap());
+
+        let data = [0xd8, 0x83, 0x9e, 0xab, 0xa4, 0xb4, 0xbe, 0xff];
+        assert_eq!("{d8}Derk0!", decode_name(&data));
+        assert_eq!(data.to_vec(), encode_name("{d8}Derk0!", 8).unwrap());
+        let data = [0x86, 0xfe, 0x86, 0xff];
+        assert_eq!("G{fe}G", decode_name(&data));
+        assert_eq!(data.to_vec(), encode_name("G{fe}G", 4).unwrap());
+        assert!(encode_name("Guard", 3).is_err());
+        assert!(encode_name("@", 3).is_err());
+    }
+}
@@ -1,5 +1,8 @@
-pub fn get_u16(data: &[u8]) -> u32 {
-    (data[0] as u32) | ((data[1] as u32) << 1)
+use simple_error::SimpleError;
+use std::error::Error;
+
+pub fn get_u16(data: &[u8]) -> u16 {
+    (data[0] as u16) | ((data[1] as u16) << 8)
 }
 
 pub fn get_u24(data: &[u8]) -> u32 {
@@ -10,3 +13,16 @@ pub fn test_bit(data: u8, bit: u8) -> bool {
     let mask = 1 << bit;
     return (data & mask) == mask;
 }
+
+// Errors unless `data` holds `len` bytes starting at `addr`.
+pub fn check_len(data: &[u8], addr: usize, len: usize) -> Result<(), Box<Error>> {
+    if data.len() < addr + len {
+        return Err(SimpleError::new(format!(
+            "data needs to be at least {} bytes long.  Is {}.",
+            addr + len,
+            data.len()
+        ))
+        .into());
+    }
+    Ok(())
+}
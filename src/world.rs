@@ -0,0 +1,332 @@
+use simple_error::SimpleError;
+use std::error::Error;
+
+use battle::encounter::{self, EncounterRate, ZoneEncounters, TERRAINS};
+use compression;
+use graphics::palette::{self, Palette};
+use graphics::tile::{Tile, TILE_SIZE};
+use graphics::{Color, Image};
+use rom_map;
+use utils::check_len;
+
+// World maps are drawn in mode 7 from 256 tiles of packed 4bpp pixels.  Each
+// tile picks one of the map's 16 color palettes.
+const NUM_TILES: usize = 0x100;
+const PACKED_TILE_LEN: usize = TILE_SIZE * TILE_SIZE / 2;
+const NUM_PALETTE_COLORS: usize = 0x80;
+const SUB_PALETTE_SIZE: usize = 0x10;
+const TILE_PROPERTY_LEN: usize = 2;
+
+const ZONE_PIXELS: usize = 32 * TILE_SIZE;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Map {
+    Balance,
+    Ruin,
+    SerpentTrench,
+}
+
+impl Map {
+    pub fn all() -> [Map; 3] {
+        [Map::Balance, Map::Ruin, Map::SerpentTrench]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Map::Balance => "balance",
+            Map::Ruin => "ruin",
+            Map::SerpentTrench => "serpent_trench",
+        }
+    }
+
+    // Width and height in tiles.
+    pub fn size(&self) -> usize {
+        match self {
+            Map::SerpentTrench => 128,
+            _ => 256,
+        }
+    }
+
+    // The Serpent Trench has no random encounters.
+    pub fn world(&self) -> Option<encounter::World> {
+        match self {
+            Map::Balance => Some(encounter::World::Balance),
+            Map::Ruin => Some(encounter::World::Ruin),
+            Map::SerpentTrench => None,
+        }
+    }
+
+    pub fn tilemap_addr(&self) -> usize {
+        match self {
+            Map::Balance => rom_map::WOB_TILEMAP,
+            Map::Ruin => rom_map::WOR_TILEMAP,
+            Map::SerpentTrench => rom_map::SERPENT_TRENCH_TILEMAP,
+        }
+    }
+
+    pub fn graphics_addr(&self) -> usize {
+        match self {
+            Map::Balance => rom_map::WOB_GRAPHICS,
+            Map::Ruin => rom_map::WOR_GRAPHICS,
+            Map::SerpentTrench => rom_map::SERPENT_TRENCH_GRAPHICS,
+        }
+    }
+
+    fn palette_addr(&self) -> usize {
+        match self {
+            Map::Balance => rom_map::WOB_PALETTE,
+            Map::Ruin => rom_map::WOR_PALETTE,
+            Map::SerpentTrench => rom_map::SERPENT_TRENCH_PALETTE,
+        }
+    }
+
+    fn tile_properties_addr(&self) -> Option<usize> {
+        match self {
+            Map::Balance => Some(rom_map::WOB_TILE_PROPERTIES),
+            Map::Ruin => Some(rom_map::WOR_TILE_PROPERTIES),
+            Map::SerpentTrench => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct TileProperties {
+    pub random_encounters: bool,
+    // Index into `encounter::TERRAINS`.
+    pub terrain: usize,
+    // Remaining bits such as airship landing and chocobo movement.
+    pub unknown_bits: u16,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct WorldMap {
+    pub map: Map,
+    // One tile index per map tile in row major order.
+    pub tilemap: Vec<u8>,
+    pub tiles: Vec<Tile>,
+    // Sub-palette of each tile.
+    pub tile_palettes: Vec<u8>,
+    pub properties: Vec<TileProperties>,
+    pub palette: Palette,
+}
+
+pub fn decode_properties(v: u16) -> TileProperties {
+    TileProperties {
+        random_encounters: v & 0x0040 == 0x0040,
+        terrain: ((v >> 8) & 0x3) as usize,
+        unknown_bits: v & !0x0340,
+    }
+}
+
+pub fn encode_properties(p: &TileProperties) -> u16 {
+    let mut v = p.unknown_bits & !0x0340 | ((p.terrain as u16 & 0x3) << 8);
+    if p.random_encounters {
+        v |= 0x0040;
+    }
+    v
+}
+
+// Packed tiles store two pixels per byte, left pixel in the low nibble.
+pub fn decode_packed_tile(data: &[u8]) -> Tile {
+    let mut pixels = [0u8; TILE_SIZE * TILE_SIZE];
+    for (i, b) in data.iter().take(PACKED_TILE_LEN).enumerate() {
+        pixels[i * 2] = b & 0xf;
+        pixels[i * 2 + 1] = b >> 4;
+    }
+    pixels
+}
+
+// The decompressed graphics hold the packed tiles followed by one palette
+// byte per tile with the sub-palette in the high nibble.
+pub fn parse(rom_data: &[u8], map: Map) -> Result<WorldMap, Box<Error>> {
+    let tilemap_addr = rom_map::snes_to_file(map.tilemap_addr());
+    check_len(rom_data, tilemap_addr, 2)?;
+    let tilemap = compression::decompress(&rom_data[tilemap_addr..])?;
+    if tilemap.len() < map.size() * map.size() {
+        return Err(SimpleError::new(format!(
+            "{} tilemap is 0x{:x} bytes.  Expected 0x{:x}.",
+            map.name(),
+            tilemap.len(),
+            map.size() * map.size()
+        ))
+        .into());
+    }
+
+    let graphics_addr = rom_map::snes_to_file(map.graphics_addr());
+    check_len(rom_data, graphics_addr, 2)?;
+    let graphics = compression::decompress(&rom_data[graphics_addr..])?;
+    let palettes_start = NUM_TILES * PACKED_TILE_LEN;
+    if graphics.len() < palettes_start + NUM_TILES {
+        return Err(SimpleError::new(format!(
+            "{} graphics are 0x{:x} bytes.  Expected 0x{:x}.",
+            map.name(),
+            graphics.len(),
+            palettes_start + NUM_TILES
+        ))
+        .into());
+    }
+
+    let properties = match map.tile_properties_addr() {
+        Some(addr) => {
+            let addr = rom_map::snes_to_file(addr);
+            check_len(rom_data, addr, NUM_TILES * TILE_PROPERTY_LEN)?;
+            rom_data[addr..addr + NUM_TILES * TILE_PROPERTY_LEN]
+                .chunks(TILE_PROPERTY_LEN)
+                .map(|c| decode_properties(c[0] as u16 | (c[1] as u16) << 8))
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let palette_addr = rom_map::snes_to_file(map.palette_addr());
+    check_len(rom_data, palette_addr, NUM_PALETTE_COLORS * 2)?;
+
+    Ok(WorldMap {
+        map: map,
+        tilemap: tilemap[..map.size() * map.size()].to_vec(),
+        tiles: graphics[..palettes_start]
+            .chunks(PACKED_TILE_LEN)
+            .map(decode_packed_tile)
+            .collect(),
+        tile_palettes: graphics[palettes_start..palettes_start + NUM_TILES]
+            .iter()
+            .map(|p| p >> 4)
+            .collect(),
+        properties: properties,
+        palette: palette::decode(&rom_data[palette_addr..palette_addr + NUM_PALETTE_COLORS * 2]),
+    })
+}
+
+impl WorldMap {
+    pub fn size(&self) -> usize {
+        self.map.size()
+    }
+
+    pub fn tile_at(&self, x: usize, y: usize) -> u8 {
+        self.tilemap[y * self.size() + x]
+    }
+
+    pub fn properties_at(&self, x: usize, y: usize) -> Option<&TileProperties> {
+        self.properties.get(self.tile_at(x, y) as usize)
+    }
+
+    pub fn render(&self) -> Image {
+        let size = self.size();
+        let mut image = Image::new(size * TILE_SIZE, size * TILE_SIZE);
+        if let Some(c) = self.palette.colors.first() {
+            image.fill(*c);
+        }
+        for y in 0..size {
+            for x in 0..size {
+                let t = self.tile_at(x, y) as usize;
+                let palette = self
+                    .palette
+                    .sub_palette(self.tile_palettes[t] as usize, SUB_PALETTE_SIZE);
+                image.draw_tile(
+                    &self.tiles[t],
+                    palette,
+                    x * TILE_SIZE,
+                    y * TILE_SIZE,
+                    false,
+                    false,
+                );
+            }
+        }
+        image
+    }
+
+    // Text grid with the terrain initial of every tile, or '.' for tiles
+    // without random encounters.
+    pub fn terrain_grid(&self) -> String {
+        let mut text = String::new();
+        for y in 0..self.size() {
+            for x in 0..self.size() {
+                text.push(match self.properties_at(x, y) {
+                    Some(p) if p.random_encounters => TERRAINS[p.terrain].chars().next().unwrap(),
+                    _ => '.',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn zone_color(zone: &ZoneEncounters) -> Color {
+    if zone.rates.iter().all(|r| *r == EncounterRate::Never) {
+        Color {
+            r: 0x80,
+            g: 0x80,
+            b: 0x80,
+            a: 0xff,
+        }
+    } else {
+        Color {
+            r: 0xff,
+            g: 0x00,
+            b: 0x00,
+            a: 0xff,
+        }
+    }
+}
+
+// Outlines each encounter zone, grey for zones without encounters and red
+// otherwise, and marks tiles without random encounters with a dot.
+pub fn overlay_zones(world: &WorldMap, image: &Image, zones: &[ZoneEncounters]) -> Image {
+    let mut image = image.clone();
+    let dot = Color {
+        r: 0xff,
+        g: 0xff,
+        b: 0xff,
+        a: 0xff,
+    };
+    for y in 0..world.size() {
+        for x in 0..world.size() {
+            match world.properties_at(x, y) {
+                Some(p) if !p.random_encounters => image.set_pixel(
+                    x * TILE_SIZE + TILE_SIZE / 2,
+                    y * TILE_SIZE + TILE_SIZE / 2,
+                    dot,
+                ),
+                _ => (),
+            }
+        }
+    }
+    let columns = world.size() * TILE_SIZE / ZONE_PIXELS;
+    for zy in 0..columns {
+        for zx in 0..columns {
+            let zone =
+                encounter::zone_at(zx * ZONE_PIXELS / TILE_SIZE, zy * ZONE_PIXELS / TILE_SIZE);
+            if let Some(z) = zones.get(zone) {
+                image.draw_rect(
+                    zx * ZONE_PIXELS,
+                    zy * ZONE_PIXELS,
+                    ZONE_PIXELS,
+                    ZONE_PIXELS,
+                    zone_color(z),
+                );
+            }
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_test() {
+        let p = decode_properties(0x0247);
+        assert!(p.random_encounters);
+        assert_eq!("desert", TERRAINS[p.terrain]);
+        assert_eq!(0x0247, encode_properties(&p));
+
+        let mut data = [0u8; PACKED_TILE_LEN];
+        data[0] = 0x21;
+        data[31] = 0xf0;
+        let t = decode_packed_tile(&data);
+        assert_eq!([1, 2], t[0..2]);
+        assert_eq!(0xf, t[63]);
+    }
+}